@@ -0,0 +1,208 @@
+use crate::{
+    binary_vdf::Reader,
+    vdf::{VdfError, VdfValue},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+/// Steam's cache of what it knows about every app, in binary VDF.
+const APPINFO_FILE: &str = "appcache/appinfo.vdf";
+
+const MAGIC_V27: u32 = 0x07564427;
+/// Adds a hash of the binary data to every app.
+const MAGIC_V28: u32 = 0x07564428;
+/// Keys are indexes into a string table at the end of the file.
+const MAGIC_V29: u32 = 0x07564429;
+
+/// Names of the genre ids used by Steam.
+const GENRES: [(&str, &str); 28] = [
+    ("1", "Action"),
+    ("2", "Strategy"),
+    ("3", "RPG"),
+    ("4", "Casual"),
+    ("9", "Racing"),
+    ("18", "Sports"),
+    ("23", "Indie"),
+    ("25", "Adventure"),
+    ("28", "Simulation"),
+    ("29", "Massively Multiplayer"),
+    ("37", "Free to Play"),
+    ("51", "Animation & Modeling"),
+    ("52", "Audio Production"),
+    ("53", "Design & Illustration"),
+    ("54", "Education"),
+    ("55", "Photo Editing"),
+    ("56", "Software Training"),
+    ("57", "Utilities"),
+    ("58", "Video Production"),
+    ("59", "Web Publishing"),
+    ("60", "Game Development"),
+    ("70", "Early Access"),
+    ("71", "Sexual Content"),
+    ("72", "Nudity"),
+    ("73", "Violent"),
+    ("74", "Gore"),
+    ("81", "Documentary"),
+    ("84", "Tutorial"),
+];
+
+/// Read the string table of a v29 file, found at `offset`.
+fn read_string_table(data: &[u8], offset: usize) -> Result<Vec<String>, VdfError> {
+    let mut reader = Reader::new(data);
+    reader.pos = offset;
+    let count = reader.u32()?;
+    (0..count).map(|_| reader.c_str()).collect()
+}
+
+/// What Steam knows about the apps of a run, read once from `appinfo.vdf`.
+#[derive(Debug, Default)]
+pub struct AppInfo {
+    /// Info of each app, by appid.
+    apps: HashMap<String, VdfValue>,
+}
+
+impl AppInfo {
+    /// Read the info Steam has about the given apps. Other apps are skipped
+    /// without being parsed.
+    pub fn read(steam_root: &Path, appids: &HashSet<&str>) -> Result<AppInfo, VdfError> {
+        let data = std::fs::read(steam_root.join(APPINFO_FILE))?;
+        let mut reader = Reader::new(&data);
+        let magic = reader.u32()?;
+        let _universe = reader.u32()?;
+        match magic {
+            MAGIC_V27 | MAGIC_V28 => {}
+            MAGIC_V29 => {
+                let offset = reader.u64()? as usize;
+                reader.strings = Some(read_string_table(&data, offset)?);
+            }
+            _ => return Err(VdfError::UnsupportedVersion(magic)),
+        }
+
+        let mut apps = HashMap::new();
+        loop {
+            let appid = reader.u32()?;
+            if appid == 0 {
+                return Ok(AppInfo { apps });
+            }
+            let size = reader.u32()? as usize;
+            let end = reader.pos + size;
+            let appid = appid.to_string();
+            if appids.contains(appid.as_str()) {
+                // State, last update, access token, hash and change number,
+                // then the hash of the binary data in newer files.
+                reader.take(40)?;
+                if magic != MAGIC_V27 {
+                    reader.take(20)?;
+                }
+                apps.insert(appid, VdfValue::Block(reader.parse_block()?));
+            }
+            reader.pos = end;
+        }
+    }
+
+    /// The value of `keys` in the `common` section of each app, by appid.
+    /// Apps without it are left out.
+    fn common(&self, keys: &[&'static str]) -> impl Iterator<Item = (String, &VdfValue)> {
+        let path = [&["appinfo", "common"][..], keys].concat();
+        self.apps
+            .iter()
+            .filter_map(move |(appid, info)| Some((appid.clone(), info.lookup(&path)?)))
+    }
+
+    /// The string `key` of the `common` section of each app, by appid. Apps
+    /// without it are left out.
+    fn common_field(&self, key: &'static str) -> HashMap<String, String> {
+        self.common(&[key])
+            .filter_map(|(appid, value)| Some((appid, value.as_str()?.to_string())))
+            .collect()
+    }
+
+    /// The type of each app, like "Game" or "Tool", by appid. Apps without a
+    /// type are left out.
+    pub fn app_types(&self) -> HashMap<String, String> {
+        self.common_field("type")
+    }
+
+    /// The name of each app, by appid. Apps without a name are left out.
+    pub fn names(&self) -> HashMap<String, String> {
+        self.common_field("name")
+    }
+
+    /// The systems each app runs on natively, like "windows", "macos" or
+    /// "linux", by appid. Apps without the list are left out.
+    pub fn platforms(&self) -> HashMap<String, Vec<String>> {
+        self.common_field("oslist")
+            .into_iter()
+            .map(|(appid, oslist)| {
+                let platforms = oslist
+                    .split(',')
+                    .map(|os| os.trim().to_lowercase())
+                    .filter(|os| !os.is_empty())
+                    .collect();
+                (appid, platforms)
+            })
+            .collect()
+    }
+
+    /// The ids of the store tags of each app, by appid. Apps without tags are
+    /// left out.
+    pub fn store_tags(&self) -> HashMap<String, Vec<u32>> {
+        self.common(&["store_tags"])
+            .filter_map(|(appid, tags)| {
+                let tags = match tags {
+                    VdfValue::Block(tags) => tags
+                        .iter()
+                        .filter_map(|(_, id)| id.as_str()?.parse().ok())
+                        .collect(),
+                    VdfValue::Str(_) => return None,
+                };
+                Some((appid, tags))
+            })
+            .collect()
+    }
+
+    /// How well each app runs on the Steam Deck, by appid: 1 for unsupported,
+    /// 2 for playable and 3 for verified. Apps not rated yet are left out.
+    pub fn deck_compat(&self) -> HashMap<String, u8> {
+        self.common(&["steam_deck_compatibility", "category"])
+            .filter_map(|(appid, category)| {
+                let category = category
+                    .as_str()?
+                    .parse()
+                    .ok()
+                    .filter(|category| *category > 0)?;
+                Some((appid, category))
+            })
+            .collect()
+    }
+
+    /// The Metacritic score of each app, by appid. Apps without a score are
+    /// left out.
+    pub fn metacritic_scores(&self) -> HashMap<String, u8> {
+        self.common_field("metacritic_score")
+            .into_iter()
+            .filter_map(|(appid, score)| Some((appid, score.parse().ok()?)))
+            .collect()
+    }
+
+    /// The genres of each app, by appid. Apps without genre data are left
+    /// out.
+    pub fn genres(&self) -> HashMap<String, Vec<String>> {
+        self.common(&["genres"])
+            .filter_map(|(appid, genres)| {
+                let genres = match genres {
+                    VdfValue::Block(genres) => genres
+                        .iter()
+                        .filter_map(|(_, id)| id.as_str())
+                        .filter_map(|id| GENRES.iter().find(|(genre, _)| *genre == id))
+                        .map(|(_, name)| name.to_string())
+                        .collect(),
+                    VdfValue::Str(_) => return None,
+                };
+                Some((appid, genres))
+            })
+            .collect()
+    }
+}
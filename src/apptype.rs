@@ -0,0 +1,203 @@
+/// App ids of Valve's runtimes and tools, which show up as installed apps.
+const RUNTIME_APPIDS: [&str; 19] = [
+    "228980",  // Steamworks Common Redistributables
+    "250820",  // SteamVR
+    "858280",  // Proton 3.7
+    "961940",  // Proton 3.16
+    "1054830", // Proton 4.2
+    "1070560", // Steam Linux Runtime
+    "1113280", // Proton 4.11
+    "1161040", // Proton BattlEye Runtime
+    "1245040", // Proton 5.0
+    "1391110", // Steam Linux Runtime - Soldier
+    "1420170", // Proton 5.13
+    "1493710", // Proton Experimental
+    "1580130", // Proton 6.3
+    "1628350", // Steam Linux Runtime - Sniper
+    "1826330", // Proton EasyAntiCheat Runtime
+    "1887720", // Proton 7.0
+    "2180100", // Proton Hotfix
+    "2348590", // Proton 8.0
+    "2805730", // Proton 9.0
+];
+
+/// Names of Steam libraries/tools we never want to launch, for when their
+/// type is unknown.
+const TOOL_NAMES: [&str; 2] = ["Steamworks Common Redistributables", "SteamVR"];
+
+/// What kind of app an installed app is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppType {
+    Game,
+    Demo,
+    /// Runtimes, dedicated servers, benchmarks and other tools.
+    Tool,
+    /// Software that isn't a game, like Blender.
+    Application,
+    /// Soundtracks.
+    Music,
+}
+
+impl AppType {
+    /// The type of an app from the `type` Steam keeps in its app info, `None`
+    /// for types that don't tell.
+    fn from_appinfo(app_type: &str) -> Option<Self> {
+        match app_type.to_lowercase().as_str() {
+            "game" | "mod" => Some(AppType::Game),
+            "demo" => Some(AppType::Demo),
+            "tool" | "config" => Some(AppType::Tool),
+            "application" => Some(AppType::Application),
+            "music" => Some(AppType::Music),
+            _ => None,
+        }
+    }
+
+    /// Whether it's something to play.
+    pub fn is_game(self) -> bool {
+        matches!(self, AppType::Game | AppType::Demo)
+    }
+}
+
+/// Words following "Proton" in the names of Proton builds and runtimes
+/// that aren't a version, like "Proton Hotfix".
+const PROTON_BUILDS: [&str; 5] = [
+    "Experimental",
+    "Hotfix",
+    "Next",
+    "EasyAntiCheat",
+    "BattlEye",
+];
+
+/// Detect if app is a Proton runtime: "Proton" followed by a version like
+/// "9.0 (Beta)" or by the name of a build like "Experimental", or a
+/// community build like "GE-Proton9-1".
+fn is_proton(app_name: &str) -> bool {
+    if app_name.strip_prefix("GE-Proton").is_some_and(|version| {
+        version.is_empty() || version.starts_with(|c: char| c.is_ascii_digit())
+    }) {
+        return true;
+    }
+    let mut words = app_name.split_whitespace();
+    if words.next() != Some("Proton") {
+        return false;
+    }
+    match words.next() {
+        Some(word) => {
+            let version = word.split('-').next().unwrap_or(word);
+            version.parse::<f32>().is_ok_and(|number| number > 0.0)
+                || PROTON_BUILDS
+                    .iter()
+                    .any(|build| build.eq_ignore_ascii_case(word))
+        }
+        None => true,
+    }
+}
+
+/// Guess from its name whether an app is a soundtrack rather than a game.
+fn is_soundtrack_name(app_name: &str) -> bool {
+    app_name.ends_with("Soundtrack") // This **should** deal with downloaded albums
+}
+
+/// Classify an app, from `appinfo_type` when Steam's app info has it, else
+/// from its appid and as a last resort from its name.
+pub fn classify(appid: &str, app_name: &str, appinfo_type: Option<&str>) -> AppType {
+    if RUNTIME_APPIDS.contains(&appid) {
+        return AppType::Tool;
+    }
+    if let Some(app_type) = appinfo_type.and_then(AppType::from_appinfo) {
+        return app_type;
+    }
+    if TOOL_NAMES.contains(&app_name)
+        || is_proton(app_name)
+        || app_name.starts_with("Steam Linux Runtime")
+        || app_name.starts_with("Steamworks Common")
+    {
+        AppType::Tool
+    } else if is_soundtrack_name(app_name) {
+        AppType::Music
+    } else {
+        AppType::Game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_known_apps() {
+        let apps = [
+            ("620", "Portal 2", Some("Game"), AppType::Game),
+            ("1245620", "ELDEN RING", None, AppType::Game),
+            (
+                "1250410",
+                "Microsoft Flight Simulator Demo",
+                Some("demo"),
+                AppType::Demo,
+            ),
+            (
+                "365670",
+                "Blender",
+                Some("Application"),
+                AppType::Application,
+            ),
+            ("1905180", "OBS Studio", Some("tool"), AppType::Tool),
+            ("1091500", "Cyberpunk 2077 Soundtrack", None, AppType::Music),
+            (
+                "1296830",
+                "Hades Original Soundtrack",
+                Some("Music"),
+                AppType::Music,
+            ),
+        ];
+        for (appid, name, appinfo_type, app_type) in apps {
+            assert_eq!(classify(appid, name, appinfo_type), app_type, "{}", name);
+        }
+    }
+
+    #[test]
+    fn recognises_proton_names() {
+        let names = [
+            ("Proton", true),
+            ("Proton 9.0 (Beta)", true),
+            ("Proton 7.0-6", true),
+            ("Proton Experimental", true),
+            ("Proton Hotfix", true),
+            ("Proton EasyAntiCheat Runtime", true),
+            ("GE-Proton", true),
+            ("GE-Proton9-1", true),
+            ("GE-Proton10-4", true),
+            ("Protonaut", false),
+            ("Proton Rush", false),
+            ("GE-Protonic Saga", false),
+        ];
+        for (name, proton) in names {
+            assert_eq!(is_proton(name), proton, "{}", name);
+        }
+    }
+
+    #[test]
+    fn runtime_names_are_tools_without_app_info() {
+        for name in [
+            "Proton Experimental",
+            "GE-Proton9-1",
+            "Steam Linux Runtime",
+            "Steam Linux Runtime 3.0 (sniper)",
+        ] {
+            assert_eq!(classify("1", name, None), AppType::Tool, "{}", name);
+        }
+    }
+
+    #[test]
+    fn runtimes_are_tools_whatever_the_app_info_says() {
+        for appid in RUNTIME_APPIDS {
+            assert_eq!(
+                classify(appid, "", Some("game")),
+                AppType::Tool,
+                "{}",
+                appid
+            );
+            assert_eq!(classify(appid, "", None), AppType::Tool, "{}", appid);
+        }
+    }
+}
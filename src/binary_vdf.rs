@@ -0,0 +1,119 @@
+use crate::vdf::{VdfError, VdfValue};
+
+// Every value starts with one of these types, then its key.
+const TYPE_BLOCK: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_POINTER: u8 = 0x04;
+const TYPE_WIDE_STRING: u8 = 0x05;
+const TYPE_COLOR: u8 = 0x06;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_END: u8 = 0x08;
+const TYPE_INT64: u8 = 0x0a;
+const TYPE_END_ALT: u8 = 0x0b;
+
+/// Reads binary VDF out of a byte buffer.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pub pos: usize,
+    /// Keys by index, for files that store them in a string table.
+    pub strings: Option<Vec<String>>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader {
+            data,
+            pos: 0,
+            strings: None,
+        }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], VdfError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(VdfError::UnexpectedEof)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, VdfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, VdfError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, VdfError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A NUL terminated string.
+    pub fn c_str(&mut self) -> Result<String, VdfError> {
+        let len = self.data[self.pos..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(VdfError::UnexpectedEof)?;
+        let s = String::from_utf8_lossy(self.take(len)?).into_owned();
+        self.pos += 1;
+        Ok(s)
+    }
+
+    /// A NUL terminated UTF-16 string.
+    fn wide_str(&mut self) -> Result<String, VdfError> {
+        let mut units = Vec::new();
+        loop {
+            let unit = u16::from_le_bytes(self.take(2)?.try_into().unwrap());
+            if unit == 0 {
+                return Ok(String::from_utf16_lossy(&units));
+            }
+            units.push(unit);
+        }
+    }
+
+    fn key(&mut self) -> Result<String, VdfError> {
+        if self.strings.is_none() {
+            return self.c_str();
+        }
+        let index = self.u32()?;
+        self.strings
+            .as_ref()
+            .and_then(|strings| strings.get(index as usize))
+            .cloned()
+            .ok_or_else(|| VdfError::MissingKey(format!("string #{}", index)))
+    }
+
+    /// Read the entries of a block up to its end marker. Numbers are kept as
+    /// text, like in text VDF.
+    pub fn parse_block(&mut self) -> Result<Vec<(String, VdfValue)>, VdfError> {
+        let mut entries = Vec::new();
+        loop {
+            let kind = self.u8()?;
+            if kind == TYPE_END || kind == TYPE_END_ALT {
+                return Ok(entries);
+            }
+            let key = self.key()?;
+            let value = match kind {
+                TYPE_BLOCK => VdfValue::Block(self.parse_block()?),
+                TYPE_STRING => VdfValue::Str(self.c_str()?),
+                TYPE_WIDE_STRING => VdfValue::Str(self.wide_str()?),
+                TYPE_INT32 | TYPE_POINTER | TYPE_COLOR => {
+                    VdfValue::Str((self.u32()? as i32).to_string())
+                }
+                TYPE_FLOAT32 => VdfValue::Str(f32::from_bits(self.u32()?).to_string()),
+                TYPE_UINT64 => VdfValue::Str(self.u64()?.to_string()),
+                TYPE_INT64 => VdfValue::Str((self.u64()? as i64).to_string()),
+                _ => return Err(VdfError::UnknownType(kind)),
+            };
+            entries.push((key, value));
+        }
+    }
+}
+
+/// Parse a whole binary VDF document, like `shortcuts.vdf`.
+pub fn parse_binary_vdf(data: &[u8]) -> Result<VdfValue, VdfError> {
+    Reader::new(data).parse_block().map(VdfValue::Block)
+}
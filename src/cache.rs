@@ -0,0 +1,186 @@
+use crate::{load_manifest, manifest_files, Game};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const CACHE_FILE: &str = "steam_randomiser/games.json";
+
+/// A game manifest as it was when it was last parsed.
+#[derive(Serialize, Deserialize)]
+struct CachedManifest {
+    path: PathBuf,
+    /// Modification time and length of the manifest, Steam rewrites it
+    /// whenever anything about the game changes.
+    modified: SystemTime,
+    len: u64,
+    game: CachedGame,
+}
+
+/// Every field of a `Game`, unlike its serialized output form.
+#[derive(Serialize, Deserialize)]
+struct CachedGame {
+    name: String,
+    appid: String,
+    library: PathBuf,
+    last_played: u64,
+    state_flags: u64,
+    size_on_disk: u64,
+    install_dir: String,
+}
+
+impl From<&Game> for CachedGame {
+    fn from(game: &Game) -> Self {
+        CachedGame {
+            name: game.name.clone(),
+            appid: game.appid.clone(),
+            library: game.library.clone(),
+            last_played: game.last_played,
+            state_flags: game.state_flags,
+            size_on_disk: game.size_on_disk,
+            install_dir: game.install_dir.clone(),
+        }
+    }
+}
+
+impl From<&CachedGame> for Game {
+    fn from(game: &CachedGame) -> Self {
+        Game {
+            name: game.name.clone(),
+            appid: game.appid.clone(),
+            library: game.library.clone(),
+            last_played: game.last_played,
+            state_flags: game.state_flags,
+            size_on_disk: game.size_on_disk,
+            playtime: None,
+            install_dir: game.install_dir.clone(),
+        }
+    }
+}
+
+/// Location of the game cache, in the user's cache directory.
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(CACHE_FILE))
+}
+
+/// Read the cache, a missing or unreadable one is just empty.
+fn load_cache(path: &Path) -> Vec<CachedManifest> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(path: &Path, manifests: &[CachedManifest]) -> std::io::Result<()> {
+    crate::write_atomic(path, serde_json::to_string(manifests)?.as_bytes())
+}
+
+/// Get the games of every library, only parsing the manifests that changed
+/// since the last run. `refresh` ignores the cache entirely.
+pub fn scan_libraries(libraries: &[PathBuf], refresh: bool) -> Vec<Game> {
+    scan_cached(libraries, cache_path().as_deref(), refresh)
+}
+
+fn scan_cached(libraries: &[PathBuf], cache_path: Option<&Path>, refresh: bool) -> Vec<Game> {
+    let cache = match cache_path {
+        Some(path) if !refresh => load_cache(path),
+        _ => Vec::new(),
+    };
+    let cached = cache
+        .iter()
+        .map(|manifest| (manifest.path.as_path(), manifest))
+        .collect::<HashMap<_, _>>();
+
+    let mut games = Vec::new();
+    let mut scanned = Vec::new();
+    let mut changed = false;
+    for library in libraries {
+        // Same as an uncached scan, a library that can't be listed is skipped.
+        let files = manifest_files(library).unwrap_or_default();
+        for file in files {
+            let stamp = std::fs::metadata(&file).and_then(|m| Ok((m.modified()?, m.len())));
+            let (modified, len) = match stamp {
+                Ok(stamp) => stamp,
+                // Nothing to cache against, parse it every time.
+                Err(_) => {
+                    games.extend(load_manifest(&file, library));
+                    continue;
+                }
+            };
+
+            let game = match cached.get(file.as_path()) {
+                Some(cached) if cached.modified == modified && cached.len == len => {
+                    Game::from(&cached.game)
+                }
+                _ => {
+                    changed = true;
+                    match load_manifest(&file, library) {
+                        Some(game) => game,
+                        None => continue,
+                    }
+                }
+            };
+
+            scanned.push(CachedManifest {
+                path: file,
+                modified,
+                len,
+                game: CachedGame::from(&game),
+            });
+            games.push(game);
+        }
+    }
+
+    changed |= scanned.len() != cache.len();
+    if let (Some(path), true) = (cache_path, changed) {
+        if let Err(err) = save_cache(path, &scanned) {
+            eprintln!("Couldn't save game cache {}: {}", path.display(), err);
+        }
+    }
+
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MANIFEST_DIR;
+
+    #[test]
+    fn rewritten_manifest_is_parsed_again() {
+        let dir =
+            std::env::temp_dir().join(format!("steam_randomiser-{}-cache", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let libraries = [dir.join("library")];
+        let cache_path = dir.join("games.json");
+        let manifest = libraries[0].join(MANIFEST_DIR).join("appmanifest_10.acf");
+        std::fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+        let write_manifest = |name: &str| {
+            let contents = format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\t\"10\"\n\t\"name\"\t\t\"{}\"\n}}\n",
+                name
+            );
+            std::fs::write(&manifest, contents).unwrap();
+        };
+        let names = || {
+            scan_cached(&libraries, Some(&cache_path), false)
+                .into_iter()
+                .map(|game| game.name)
+                .collect::<Vec<_>>()
+        };
+
+        write_manifest("Old Name");
+        assert_eq!(names(), ["Old Name"]);
+
+        // An unchanged manifest comes out of the cache.
+        let mut cache = load_cache(&cache_path);
+        cache[0].game.name = "Cached Name".to_string();
+        save_cache(&cache_path, &cache).unwrap();
+        assert_eq!(names(), ["Cached Name"]);
+
+        write_manifest("A Longer New Name");
+        assert_eq!(names(), ["A Longer New Name"]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    userdata::user_dirs,
+    vdf::{parse_vdf, VdfValue},
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+/// Where current Steam keeps collections, among other synced settings.
+const CLOUD_STORAGE_FILE: &str = "config/cloudstorage/cloud-storage-namespace-1.json";
+const COLLECTION_KEY_PREFIX: &str = "user-collections.";
+
+/// Where older Steam kept categories, as tags of each app.
+const SHARED_CONFIG_FILE: &str = "7/remote/sharedconfig.vdf";
+const SHARED_APPS_KEY: [&str; 5] = [
+    "UserRoamingConfigStore",
+    "Software",
+    "Valve",
+    "Steam",
+    "apps",
+];
+
+/// A synced setting, a collection if its key says so.
+#[derive(Deserialize)]
+struct CloudEntry {
+    #[serde(default)]
+    is_deleted: bool,
+    /// JSON of the setting.
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CloudCollection {
+    name: String,
+    #[serde(default)]
+    added: Vec<u64>,
+    #[serde(default)]
+    removed: Vec<u64>,
+}
+
+/// Appids of each collection, by name.
+pub type Collections = HashMap<String, HashSet<String>>;
+
+/// Add the collections of a user's cloud storage. Dynamic collections only
+/// have the games added or removed by hand.
+fn read_cloud_collections(path: &Path, collections: &mut Collections) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let entries = serde_json::from_str::<Vec<(String, CloudEntry)>>(&contents)
+        .map_err(|err| err.to_string())?;
+    for (key, entry) in entries {
+        let value = match entry.value {
+            Some(value) if key.starts_with(COLLECTION_KEY_PREFIX) && !entry.is_deleted => value,
+            _ => continue,
+        };
+        let collection = match serde_json::from_str::<CloudCollection>(&value) {
+            Ok(collection) => collection,
+            Err(_) => continue,
+        };
+        let apps = collections.entry(collection.name).or_default();
+        apps.extend(
+            collection
+                .added
+                .iter()
+                .filter(|appid| !collection.removed.contains(appid))
+                .map(|appid| appid.to_string()),
+        );
+    }
+    Ok(())
+}
+
+/// Add the categories of a user's `sharedconfig.vdf`.
+fn read_shared_categories(path: &Path, collections: &mut Collections) -> Result<(), String> {
+    let config = parse_vdf(path).map_err(|err| err.to_string())?;
+    let apps = match config.lookup(&SHARED_APPS_KEY) {
+        Some(VdfValue::Block(apps)) => apps,
+        _ => return Ok(()),
+    };
+    for (appid, app) in apps {
+        let tags = match app.get("tags") {
+            Some(VdfValue::Block(tags)) => tags,
+            _ => continue,
+        };
+        for name in tags.iter().filter_map(|(_, tag)| tag.as_str()) {
+            collections
+                .entry(name.to_string())
+                .or_default()
+                .insert(appid.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Read the collections of `steam_id`, or of every account if not given,
+/// from the cloud storage of current Steam or else the categories of older
+/// Steam.
+pub fn read_collections(steam_root: &Path, steam_id: Option<u64>) -> Result<Collections, String> {
+    let users = user_dirs(steam_root, steam_id);
+    let mut collections = Collections::new();
+    let mut found = false;
+    for user in &users {
+        let cloud_storage = user.join(CLOUD_STORAGE_FILE);
+        let shared_config = user.join(SHARED_CONFIG_FILE);
+        let (path, result) = if cloud_storage.is_file() {
+            let result = read_cloud_collections(&cloud_storage, &mut collections);
+            (cloud_storage, result)
+        } else if shared_config.is_file() {
+            let result = read_shared_categories(&shared_config, &mut collections);
+            (shared_config, result)
+        } else {
+            continue;
+        };
+        if let Err(err) = result {
+            return Err(format!("couldn't read {}: {}", path.display(), err));
+        }
+        found = true;
+    }
+    match (found, users.first()) {
+        (true, _) => Ok(collections),
+        (false, Some(user)) => Err(format!(
+            "expected them in {} or {}",
+            user.join(CLOUD_STORAGE_FILE).display(),
+            user.join(SHARED_CONFIG_FILE).display()
+        )),
+        (false, None) => Err(format!(
+            "no Steam account has data in {}",
+            steam_root.join("userdata").display()
+        )),
+    }
+}
@@ -0,0 +1,69 @@
+use crate::DeckCompat;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use steam_randomiser::SteamKindArg;
+
+const CONFIG_FILE: &str = "steam_randomiser/config.toml";
+
+/// Defaults for the command line options, read from the config file. Keys are
+/// named like the options.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    pub verbose: Option<u8>,
+    /// Never launch the pick, like `--dry-run`.
+    pub dry_run: bool,
+    /// Announce the pick with a desktop notification, like `--notify`.
+    pub notify: bool,
+    pub steam_kind: Option<SteamKindArg>,
+    /// Names or `appid:<id>` entries never picked, on top of the blacklist
+    /// file.
+    pub blacklist: Vec<String>,
+    /// Libraries to scan besides the ones Steam knows about.
+    pub libraries: Vec<PathBuf>,
+    /// Steam Web API key for `--pool owned`.
+    pub api_key: Option<String>,
+    pub filters: Filters,
+}
+
+/// Default filters, same as the options of the same name.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Filters {
+    pub exclude: Vec<String>,
+    pub exclude_id: Vec<String>,
+    pub filter: Option<String>,
+    pub genre: Option<String>,
+    pub collection: Option<String>,
+    pub include_uninstalled: bool,
+    pub include_tools: bool,
+    pub never_played: bool,
+    pub not_played_within: Option<u64>,
+    pub no_repeat: Option<usize>,
+    pub exclude_vr: bool,
+    pub native_only: bool,
+    pub deck_compat: Option<DeckCompat>,
+    /// Sizes like the options take them, e.g. `500MB`.
+    pub min_size: Option<String>,
+    pub max_size: Option<String>,
+}
+
+/// Location of the config file, in the user's config directory.
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Read the config file. Unknown keys are only warned about so a config
+/// written for another version still works.
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut deserializer = toml::Deserializer::new(&contents);
+    serde_ignored::deserialize(&mut deserializer, |key| {
+        eprintln!(
+            "Warning: ignoring unknown key \"{}\" in {}",
+            key,
+            path.display()
+        );
+    })
+    .map_err(|err| err.to_string())
+}
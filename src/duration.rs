@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// Units accepted by `parse_duration`, with their length in seconds.
+const UNITS: [(char, u64); 3] = [('h', 3600), ('m', 60), ('s', 1)];
+
+/// Parse a human friendly duration like `90s`, `20m` or `1h30m`. A number
+/// without unit is in minutes.
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration \"{}\", use e.g. 90s, 20m or 1h30m",
+            duration
+        )
+    };
+    let text = duration.trim();
+    if let Ok(minutes) = text.parse::<u64>() {
+        return match minutes {
+            0 => Err(invalid()),
+            _ => Ok(Duration::from_secs(minutes * 60)),
+        };
+    }
+
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = UNITS
+            .iter()
+            .find(|(name, _)| *name == c.to_ascii_lowercase())
+            .ok_or_else(invalid)?;
+        seconds += number.parse::<u64>().map_err(|_| invalid())? * unit.1;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format a duration for humans, e.g. `1h 05m 09s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
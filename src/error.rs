@@ -0,0 +1,64 @@
+use crate::{vdf::VdfError, MANIFEST_DIR};
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Exit code when no Steam install could be found.
+pub const EXIT_STEAM_NOT_FOUND: i32 = 2;
+/// Exit code when no game is left to pick from.
+pub const EXIT_NO_GAMES: i32 = 3;
+/// Exit code when a --filter isn't a valid regex.
+pub const EXIT_INVALID_FILTER: i32 = 4;
+
+/// Why finding Steam, its games or one to play failed.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("couldn't read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A file Steam wrote isn't valid VDF.
+    #[error("couldn't read {}: {source}", path.display())]
+    Vdf { path: PathBuf, source: VdfError },
+    /// No Steam install was found, of the kind if one was asked for.
+    #[error("{}", match .0 {
+        Some(kind) => format!("couldn't find a {} Steam install", kind),
+        None => "couldn't find Steam, please make sure it is installed".to_string(),
+    })]
+    SteamNotFound(Option<String>),
+    /// A directory given as Steam's has no libraries.
+    #[error(
+        "{} doesn't look like a Steam directory, it has no {} folder",
+        .0.display(),
+        MANIFEST_DIR
+    )]
+    NotSteamDir(PathBuf),
+    /// There's no home directory to look for Steam in.
+    #[error("couldn't find your home directory")]
+    NoHomeDir,
+    /// A --filter that isn't a valid regex.
+    #[error("invalid --filter regex:\n{0}")]
+    InvalidFilter(String),
+    /// No game is left to pick from, and why.
+    #[error("{0}")]
+    NoGames(String),
+    /// Steam's app info couldn't be read for a filter that needs `what` from
+    /// it.
+    #[error("couldn't read the {what} of games: {reason}")]
+    AppInfo { what: &'static str, reason: String },
+    /// Anything else, as the message to show.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Exit code to end the program with because of this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::SteamNotFound(_) | Error::NotSteamDir(_) => EXIT_STEAM_NOT_FOUND,
+            Error::NoGames(_) => EXIT_NO_GAMES,
+            Error::InvalidFilter(_) => EXIT_INVALID_FILTER,
+            _ => 1,
+        }
+    }
+}
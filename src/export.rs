@@ -0,0 +1,89 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use steam_randomiser::Game;
+
+const CSV_HEADER: [&str; 6] = [
+    "appid",
+    "name",
+    "library",
+    "size_on_disk",
+    "state_flags",
+    "last_played",
+];
+
+/// Where `--export` writes the games, as given by `csv[:PATH]`.
+#[derive(Clone, Debug)]
+pub struct Export {
+    /// File to write, stdout if not given.
+    pub path: Option<PathBuf>,
+}
+
+/// Parse an `--export` value, `csv` or `csv:PATH`.
+pub fn parse_export(export: &str) -> Result<Export, String> {
+    let (format, path) = match export.split_once(':') {
+        Some((format, path)) => (format, Some(PathBuf::from(path))),
+        None => (export, None),
+    };
+    match format.eq_ignore_ascii_case("csv") {
+        true => Ok(Export { path }),
+        false => Err(format!(
+            "unknown format \"{}\", only csv is supported",
+            format
+        )),
+    }
+}
+
+/// Quote a CSV field if it needs it, doubling the quotes inside.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// The columns of `game` in the order of `CSV_HEADER`. Unknown sizes and
+/// last played times are left empty.
+fn csv_row(game: &Game) -> [String; 6] {
+    let known = |value: u64| match value {
+        0 => String::new(),
+        value => value.to_string(),
+    };
+    [
+        game.appid.clone(),
+        game.name.clone(),
+        game.library.display().to_string(),
+        known(game.size_on_disk),
+        game.state_flags.to_string(),
+        known(game.last_played),
+    ]
+}
+
+fn write_csv(mut out: impl Write, games: &[Game]) -> std::io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER.join(","))?;
+    for game in games {
+        let row = csv_row(game).map(|field| csv_field(&field));
+        writeln!(out, "{}", row.join(","))?;
+    }
+    out.flush()
+}
+
+/// Write `games` as CSV to the file of `export`, or stdout. An existing
+/// file is only replaced if `force`.
+pub fn export_games(export: &Export, games: &[Game], force: bool) -> std::io::Result<()> {
+    match &export.path {
+        Some(path) => write_csv(create_file(path, force)?, games),
+        None => write_csv(std::io::stdout().lock(), games),
+    }
+}
+
+fn create_file(path: &Path, force: bool) -> std::io::Result<std::fs::File> {
+    OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(force)
+        .create_new(!force)
+        .open(path)
+}
@@ -0,0 +1,835 @@
+use crate::{
+    history::{history_path, load_history},
+    is_blacklisted, is_wanted, load_game_list, name_matches,
+    stats::LibraryStats,
+    Blacklist, DeckCompat, FilterMode, Opts, Weighting, DECK_PLAYABLE, DECK_UNSUPPORTED,
+    LEAST_PLAYED_FALLBACK, NATIVE_PLATFORM,
+};
+use regex::RegexBuilder;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use steam_randomiser::{
+    appinfo::AppInfo,
+    apptype::{classify, AppType},
+    collections::read_collections,
+    error::Error,
+    store::{Store, TAG_NAMES_MAX_AGE},
+    unix_now,
+    userdata::{read_app_stats, read_shortcuts, read_user_apps},
+    vdf::VdfError,
+    Game, STATE_FULLY_INSTALLED,
+};
+
+/// Everything the filters look at besides the options.
+pub struct GameFilter<'a> {
+    pub opts: &'a Opts,
+    pub steam_root: &'a Path,
+    /// Account whose games, playtimes and collections are used, if any.
+    pub user: Option<u64>,
+    pub appinfo: &'a Result<AppInfo, VdfError>,
+    pub blacklist: &'a Blacklist,
+    /// Games given as arguments or on stdin, to pick among.
+    pub given_games: &'a [String],
+}
+
+/// The games left to pick from, and what the output needs to know about
+/// how they were filtered.
+pub struct Filtered {
+    pub games: Vec<Game>,
+    pub stats: LibraryStats,
+    /// Controller support of the games, when --controller looked it up.
+    pub controller_support: HashMap<String, String>,
+    /// Genres of the games, for --list-genres.
+    pub genres: Option<HashMap<String, Vec<String>>>,
+    /// Why no game is left, when --filter matched none.
+    pub no_match: Option<String>,
+}
+
+/// Sort games by appid and keep one per appid. A game can have a manifest in
+/// several libraries after being moved, keep the first one so it isn't more
+/// likely to be picked.
+fn dedup_games(games: &mut Vec<Game>) {
+    // Directory iteration order isn't stable, sort so seeded picks are. The
+    // sort is stable so the first game is still the one from the first
+    // library scanned.
+    games.sort_by(|a, b| a.appid.cmp(&b.appid));
+    games.dedup_by(|a, b| a.appid == b.appid);
+}
+
+impl GameFilter<'_> {
+    /// Filter the games found down to those that can be picked, in the order
+    /// of the options' documentation. --list-genres stops before the genre
+    /// and later filters, to list the genres they'd pick from.
+    pub fn apply(&self, mut games: Vec<Game>) -> Result<Filtered, Error> {
+        let mut stats = LibraryStats::new(&games);
+        self.by_library(&mut games, &mut stats);
+        self.by_account(&mut games, &mut stats)?;
+        self.by_playtime(&mut games, &mut stats);
+        let no_match = self.by_name(&mut games, &mut stats)?;
+        let controller_support = self.by_store(&mut games, &mut stats);
+        let mut filtered = Filtered {
+            games,
+            stats,
+            controller_support,
+            genres: None,
+            no_match,
+        };
+        if self.opts.list_genres {
+            let appids = filtered
+                .games
+                .iter()
+                .map(|game| game.appid.as_str())
+                .collect::<HashSet<_>>();
+            let mut genres = self.genres()?;
+            genres.retain(|appid, _| appids.contains(appid.as_str()));
+            filtered.genres = Some(genres);
+            return Ok(filtered);
+        }
+        self.by_app_info(&mut filtered.games, &mut filtered.stats)?;
+        self.by_size_and_history(&mut filtered.games, &mut filtered.stats);
+        Ok(filtered)
+    }
+
+    /// Drop blacklisted games, games of other app types, games that aren't
+    /// installed and duplicate manifests.
+    fn by_library(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) {
+        let opts = self.opts;
+        let blacklist = self.blacklist;
+        // Ids aren't ambiguous like names, filter by them first.
+        if !opts.exclude_id.is_empty() || !opts.only_appid.is_empty() {
+            let before = games.len();
+            games.retain(|game| !opts.exclude_id.contains(&game.appid));
+            if !opts.only_appid.is_empty() {
+                games.retain(|game| opts.only_appid.contains(&game.appid));
+            }
+            stats.exclude("--exclude-id/--only-appid", before, games);
+        }
+
+        let found = games.len();
+        games.retain(|game| !is_blacklisted(&game.name, &game.appid, blacklist));
+        stats.exclude("blacklist", found, games);
+        if opts.verbose > 1 {
+            info!(opts, "{} games are blacklisted", found - games.len());
+        }
+
+        let app_types = match self.appinfo {
+            Ok(appinfo) => appinfo.app_types(),
+            Err(err) => {
+                if opts.verbose > 1 {
+                    info!(
+                        opts,
+                        "Couldn't read app types, guessing from names: {}", err
+                    );
+                }
+                Default::default()
+            }
+        };
+        let app_type = |game: &Game| {
+            classify(
+                &game.appid,
+                &game.name,
+                app_types.get(&game.appid).map(String::as_str),
+            )
+        };
+        let before = games.len();
+        if opts.music {
+            // Only ask the store about apps Steam didn't give a type for, the name
+            // is the last resort.
+            let mut store = Store::open();
+            games.retain(|game| {
+                if !app_types.contains_key(&game.appid) {
+                    if let Some(details) = store.details(&game.appid) {
+                        return details.is_music();
+                    }
+                }
+                app_type(game) == AppType::Music
+            });
+            store.save();
+        } else {
+            games.retain(|game| {
+                let app_type = app_type(game);
+                app_type.is_game() || (opts.include_tools && app_type != AppType::Music)
+            });
+        }
+        stats.exclude("app type", before, games);
+
+        if !opts.include_uninstalled {
+            let before = games.len();
+            games.retain(Game::is_fully_installed);
+            stats.exclude("not fully installed", before, games);
+            if !opts.no_verify {
+                let installed = games.len();
+                games.retain(Game::install_dir_exists);
+                stats.exclude("missing install folder", installed, games);
+                if opts.verbose > 0 && games.len() < installed {
+                    info!(
+                        opts,
+                        "Skipped {} games whose install folder is missing",
+                        installed - games.len()
+                    );
+                }
+            }
+        }
+
+        let before = games.len();
+        dedup_games(games);
+        stats.exclude("duplicate manifest", before, games);
+    }
+
+    /// Keep the games of the --user and its --collection, adding non-Steam
+    /// shortcuts, and read their playtimes if needed.
+    fn by_account(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) -> Result<(), Error> {
+        let opts = self.opts;
+        let (steam_root, user, blacklist) = (self.steam_root, self.user, self.blacklist);
+        if let (Some(name), Some(steam_id)) = (&opts.user, user) {
+            match read_user_apps(steam_root, steam_id) {
+                Ok(apps) => {
+                    let before = games.len();
+                    games.retain(|game| apps.contains(&game.appid));
+                    stats.exclude("--user", before, games);
+                }
+                Err(err) => {
+                    return Err(Error::Other(format!(
+                        "couldn't read the local config of user {}: {}",
+                        name, err
+                    )))
+                }
+            }
+        }
+
+        if opts.include_shortcuts {
+            games.extend(
+                read_shortcuts(steam_root, user)
+                    .into_iter()
+                    .filter(|shortcut| {
+                        !is_blacklisted(&shortcut.name, &shortcut.game_id, blacklist)
+                    })
+                    .map(|shortcut| Game {
+                        name: shortcut.name,
+                        appid: shortcut.game_id,
+                        library: shortcut.start_dir,
+                        last_played: 0,
+                        state_flags: STATE_FULLY_INSTALLED,
+                        size_on_disk: 0,
+                        playtime: None,
+                        install_dir: String::new(),
+                    }),
+            );
+            stats.add(games);
+        }
+
+        if let Some(name) = &opts.collection {
+            let collections = match read_collections(steam_root, user) {
+                Ok(collections) => collections,
+                Err(err) => {
+                    return Err(Error::Other(format!(
+                        "couldn't read the Steam collections, {}",
+                        err
+                    )))
+                }
+            };
+            let apps = collections
+                .iter()
+                .find(|(collection, _)| collection.eq_ignore_ascii_case(name))
+                .map(|(_, apps)| apps);
+            match apps {
+                Some(apps) => {
+                    let before = games.len();
+                    games.retain(|game| apps.contains(&game.appid));
+                    stats.exclude("--collection", before, games);
+                }
+                None => {
+                    let mut names = collections.keys().cloned().collect::<Vec<_>>();
+                    names.sort();
+                    return Err(Error::Other(format!(
+                        "no collection named \"{}\", there are: {}",
+                        name,
+                        names.join(", ")
+                    )));
+                }
+            }
+        }
+
+        // The TUI can toggle never played games on.
+        if opts.never_played
+            || opts.tui
+            || opts.not_played_within.is_some()
+            || opts.weighting().is_some_and(Weighting::uses_playtime)
+        {
+            let stats = read_app_stats(steam_root, user);
+            for game in games.iter_mut() {
+                let app_stats = stats.get(&game.appid).copied().unwrap_or_default();
+                game.playtime = app_stats.playtime.or(game.playtime);
+                game.last_played = game.last_played.max(app_stats.last_played.unwrap_or(0));
+            }
+        }
+        Ok(())
+    }
+
+    fn by_playtime(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) {
+        let opts = self.opts;
+        if let Some(days) = opts.not_played_within {
+            let now = unix_now();
+            let before = games.len();
+            games.retain(|game| !game.played_within(days, now));
+            stats.exclude("--not-played-within", before, games);
+            if opts.verbose > 0 {
+                info!(
+                    opts,
+                    "{} games played in the last {} days were excluded",
+                    before - games.len(),
+                    days
+                );
+            }
+        }
+
+        if opts.never_played {
+            let before = games.len();
+            let unplayed = games
+                .iter()
+                .filter(|game| game.is_unplayed())
+                .cloned()
+                .collect::<Vec<_>>();
+            if unplayed.is_empty() && !games.is_empty() {
+                // Rather than failing, fall back to the games closest to unplayed.
+                let mut least_played = games.clone();
+                least_played.sort_by_key(|game| game.playtime);
+                least_played.truncate(LEAST_PLAYED_FALLBACK);
+                least_played.sort_by(|a, b| a.appid.cmp(&b.appid));
+                eprintln!("You played every game! Picking from the least played ones instead:");
+                for game in &least_played {
+                    eprintln!("  {} ({} minutes)", game.name, game.playtime.unwrap_or(0));
+                }
+                *games = least_played;
+            } else {
+                *games = unplayed;
+            }
+            stats.exclude("--never-played", before, games);
+        }
+    }
+
+    /// Filter by name and app id, returns why nothing is left if --filter
+    /// matched nothing.
+    fn by_name(
+        &self,
+        games: &mut Vec<Game>,
+        stats: &mut LibraryStats,
+    ) -> Result<Option<String>, Error> {
+        let opts = self.opts;
+        let given_games = self.given_games;
+        for exclude in &opts.exclude {
+            if !games.iter().any(|game| name_matches(&game.name, exclude)) {
+                eprintln!("Warning: --exclude \"{}\" didn't match any game", exclude);
+            }
+        }
+        let before = games.len();
+        games.retain(|game| !opts.exclude.iter().any(|e| name_matches(&game.name, e)));
+        if !opts.exclude.is_empty() {
+            stats.exclude("--exclude", before, games);
+        }
+
+        if let Some(file) = &opts.only_from {
+            let wanted = match load_game_list(file) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    return Err(Error::Other(format!(
+                        "couldn't read {}: {}",
+                        file.display(),
+                        err
+                    )))
+                }
+            };
+            for entry in &wanted {
+                if !games.iter().any(|game| is_wanted(game, entry)) {
+                    eprintln!(
+                        "Warning: \"{}\" from {} isn't installed",
+                        entry,
+                        file.display()
+                    );
+                }
+            }
+            let before = games.len();
+            games.retain(|game| wanted.iter().any(|entry| is_wanted(game, entry)));
+            stats.exclude("--only-from", before, games);
+        }
+
+        if !given_games.is_empty() {
+            for entry in given_games {
+                if !games.iter().any(|game| is_wanted(game, entry)) {
+                    eprintln!(
+                        "Warning: \"{}\" doesn't match any game left to pick from",
+                        entry
+                    );
+                }
+            }
+            let before = games.len();
+            games.retain(|game| given_games.iter().any(|entry| is_wanted(game, entry)));
+            stats.exclude("games given", before, games);
+        }
+
+        let mut no_match = None;
+        if !opts.filter.is_empty() {
+            let mut regexes = Vec::new();
+            for filter in &opts.filter {
+                match RegexBuilder::new(filter).case_insensitive(true).build() {
+                    Ok(regex) => regexes.push(regex),
+                    Err(err) => return Err(Error::InvalidFilter(err.to_string())),
+                }
+            }
+            let before = games.len();
+            games.retain(|game| match opts.filter_mode {
+                FilterMode::Any => regexes.iter().any(|regex| regex.is_match(&game.name)),
+                FilterMode::All => regexes.iter().all(|regex| regex.is_match(&game.name)),
+            });
+            stats.exclude("--filter", before, games);
+            let filters = opts
+                .filter
+                .iter()
+                .map(|filter| format!("\"{}\"", filter))
+                .collect::<Vec<_>>()
+                .join(", ");
+            // Only stops the pick, --list and the like still show there's nothing.
+            if games.is_empty() {
+                no_match = Some(format!(
+                    "no games matched filter {} ({} games filtered out)",
+                    filters, before
+                ));
+            }
+            if opts.verbose > 0 && opts.dry_run {
+                info!(opts, "{} games matched filter {}:", games.len(), filters);
+                for game in games.iter() {
+                    info!(opts, "  {}", game.name);
+                }
+            }
+        }
+
+        Ok(no_match)
+    }
+
+    /// Filter by what the Steam store knows, returns the controller support
+    /// of the games if it was looked up.
+    fn by_store(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) -> HashMap<String, String> {
+        let opts = self.opts;
+        if opts.exclude_vr || opts.vr_only {
+            let before = games.len();
+            let mut store = Store::open();
+            games.retain(|game| match store.details(&game.appid) {
+                Some(details) => details.is_vr() == opts.vr_only,
+                None => opts.exclude_vr,
+            });
+            store.save();
+            let reason = match opts.vr_only {
+                true => "--vr-only",
+                false => "--exclude-vr",
+            };
+            stats.exclude(reason, before, games);
+        }
+
+        if let Some(players) = opts.players.filter(|players| *players > 1) {
+            let before = games.len();
+            let mut store = Store::open();
+            let mut unknown = 0;
+            games.retain(|game| match store.details(&game.appid) {
+                Some(details) => details.is_multiplayer(opts.local),
+                None => {
+                    unknown += 1;
+                    false
+                }
+            });
+            store.save();
+            if unknown > 0 {
+                eprintln!(
+                    "Warning: {} games the store can't tell the players of were skipped",
+                    unknown
+                );
+            }
+            stats.exclude("--players", before, games);
+            if opts.verbose > 0 {
+                info!(opts, "{} games for {} players left", games.len(), players);
+            }
+        }
+
+        let mut controller_support = HashMap::new();
+        if let Some(level) = opts.controller {
+            let before = games.len();
+            let mut store = Store::open();
+            games.retain(|game| {
+                let support = match store.details(&game.appid) {
+                    Some(details) => details.controller_support.as_deref().unwrap_or("none"),
+                    None => "unknown",
+                };
+                controller_support.insert(game.appid.clone(), support.to_string());
+                level.allows(support, opts.controller_unknown_ok)
+            });
+            store.save();
+            stats.exclude("--controller", before, games);
+        }
+
+        controller_support
+    }
+
+    /// Genres of every game, by appid.
+    fn genres(&self) -> Result<HashMap<String, Vec<String>>, Error> {
+        match self.appinfo {
+            Ok(appinfo) => Ok(appinfo.genres()),
+            Err(err) => Err(Error::AppInfo {
+                what: "genres",
+                reason: err.to_string(),
+            }),
+        }
+    }
+
+    /// Filter by what Steam's app info knows.
+    fn by_app_info(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) -> Result<(), Error> {
+        let opts = self.opts;
+        if !opts.genre.is_empty() {
+            let genres = self.genres()?;
+            let before = games.len();
+            let mut unknown = 0;
+            games.retain(|game| match genres.get(&game.appid) {
+                Some(names) => names.iter().any(|name| {
+                    opts.genre
+                        .iter()
+                        .any(|genre| name.eq_ignore_ascii_case(genre))
+                }),
+                None => {
+                    unknown += 1;
+                    false
+                }
+            });
+            if unknown > 0 {
+                eprintln!(
+                    "Warning: {} games have no known genre and were skipped",
+                    unknown
+                );
+            }
+            stats.exclude("--genre", before, games);
+        }
+
+        if !opts.tag.is_empty() {
+            let app_tags = match self.appinfo {
+                Ok(appinfo) => appinfo.store_tags(),
+                Err(err) => {
+                    return Err(Error::AppInfo {
+                        what: "store tags",
+                        reason: err.to_string(),
+                    })
+                }
+            };
+            // Games only have tag ids, the store has their names.
+            let mut store = Store::open();
+            let tag_names = store.tag_names().clone();
+            let age = store.tag_names_age();
+            store.save();
+            if tag_names.is_empty() {
+                return Err(Error::Other(
+                    "couldn't get the names of the store tags, is the Steam store reachable?"
+                        .to_string(),
+                ));
+            }
+            let days = age.as_secs() / (24 * 60 * 60);
+            if age > TAG_NAMES_MAX_AGE {
+                eprintln!(
+                    "Warning: the Steam store can't be reached, using store tags cached {} days ago",
+                    days
+                );
+            } else if opts.verbose > 1 {
+                info!(opts, "Using store tags cached {} days ago", days);
+            }
+            let before = games.len();
+            let mut unknown = 0;
+            games.retain(|game| {
+                let names = match app_tags.get(&game.appid) {
+                    Some(ids) => ids
+                        .iter()
+                        .filter_map(|id| tag_names.get(id))
+                        .collect::<Vec<_>>(),
+                    None => {
+                        unknown += 1;
+                        return false;
+                    }
+                };
+                let has_tag =
+                    |tag: &String| names.iter().any(|name| name.eq_ignore_ascii_case(tag));
+                match opts.any_tag {
+                    true => opts.tag.iter().any(has_tag),
+                    false => opts.tag.iter().all(has_tag),
+                }
+            });
+            if unknown > 0 {
+                eprintln!(
+                    "Warning: {} games have no known store tags and were skipped",
+                    unknown
+                );
+            }
+            stats.exclude("--tag", before, games);
+        }
+
+        if opts.deck_compat != DeckCompat::Any {
+            let categories = match self.appinfo {
+                Ok(appinfo) => appinfo.deck_compat(),
+                Err(err) => {
+                    return Err(Error::AppInfo {
+                        what: "Steam Deck ratings",
+                        reason: err.to_string(),
+                    })
+                }
+            };
+            let before = games.len();
+            let mut dropped = HashMap::<Option<u8>, usize>::new();
+            games.retain(|game| {
+                let category = categories.get(&game.appid).copied();
+                let allowed = opts.deck_compat.allows(category);
+                if !allowed {
+                    *dropped.entry(category).or_default() += 1;
+                }
+                allowed
+            });
+            stats.exclude("--deck-compat", before, games);
+            if opts.verbose > 0 {
+                let tiers = [
+                    (Some(DECK_PLAYABLE), "rated playable"),
+                    (Some(DECK_UNSUPPORTED), "rated unsupported"),
+                    (None, "not rated"),
+                ];
+                for (category, tier) in tiers {
+                    if let Some(count) = dropped.get(&category) {
+                        info!(
+                            opts,
+                            "{} {} games on the Steam Deck were excluded", count, tier
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(min_score) = opts.min_metacritic {
+            let scores = match self.appinfo {
+                Ok(appinfo) => appinfo.metacritic_scores(),
+                Err(err) => {
+                    return Err(Error::AppInfo {
+                        what: "Metacritic scores",
+                        reason: err.to_string(),
+                    })
+                }
+            };
+            let before = games.len();
+            let unscored = games
+                .iter()
+                .filter(|game| !scores.contains_key(&game.appid))
+                .count();
+            games.retain(|game| {
+                scores
+                    .get(&game.appid)
+                    .is_some_and(|score| *score >= min_score)
+            });
+            if opts.verbose > 0 {
+                info!(
+                    opts,
+                    "{} games scored below {} and {} without a score were excluded",
+                    before - games.len() - unscored,
+                    min_score,
+                    unscored
+                );
+            }
+            stats.exclude("--min-metacritic", before, games);
+        }
+
+        if opts.native_only {
+            let platforms = match self.appinfo {
+                Ok(appinfo) => appinfo.platforms(),
+                Err(err) => {
+                    return Err(Error::AppInfo {
+                        what: "platforms",
+                        reason: err.to_string(),
+                    })
+                }
+            };
+            let before = games.len();
+            games.retain(|game| match platforms.get(&game.appid) {
+                Some(platforms) => platforms.iter().any(|os| os == NATIVE_PLATFORM),
+                None => true,
+            });
+            stats.exclude("--native-only", before, games);
+            if opts.verbose > 0 {
+                info!(
+                    opts,
+                    "{} games don't run natively and were excluded",
+                    before - games.len()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn by_size_and_history(&self, games: &mut Vec<Game>, stats: &mut LibraryStats) {
+        let opts = self.opts;
+        let size_filter = opts.min_size.is_some() || opts.max_size.is_some();
+        if size_filter {
+            let min_size = opts.min_size.unwrap_or(0);
+            let max_size = opts.max_size.unwrap_or(u64::MAX);
+            let unknown = games.iter().filter(|game| game.size_on_disk == 0).count();
+            if unknown > 0 {
+                eprintln!(
+                    "Warning: {} games have an unknown size and were {}",
+                    unknown,
+                    if opts.strict_size {
+                        "excluded"
+                    } else {
+                        "kept (use --strict-size to exclude them)"
+                    }
+                );
+            }
+            let before = games.len();
+            games.retain(|game| game.fits_size(min_size, max_size, opts.strict_size));
+            stats.exclude("--min-size/--max-size", before, games);
+        }
+
+        if let Some(no_repeat) = opts.no_repeat {
+            let history = history_path()
+                .map(|file| load_history(&file))
+                .unwrap_or_default();
+            let recent = history
+                .iter()
+                .rev()
+                .take(no_repeat)
+                .map(|entry| &entry.appid)
+                .collect::<Vec<_>>();
+            let fresh = games
+                .iter()
+                .filter(|game| !recent.contains(&&game.appid))
+                .cloned()
+                .collect::<Vec<_>>();
+            if fresh.is_empty() {
+                eprintln!("Every game was launched recently, ignoring --no-repeat.");
+            } else {
+                let before = games.len();
+                *games = fresh;
+                stats.exclude("--no-repeat", before, games);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    fn game(appid: &str, library: &str) -> Game {
+        Game {
+            name: format!("Game {}", appid),
+            appid: appid.to_string(),
+            library: PathBuf::from(library),
+            last_played: 0,
+            state_flags: STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime: None,
+            install_dir: String::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_first_of_a_game_in_two_libraries() {
+        let mut games = vec![
+            game("20", "/first"),
+            game("10", "/first"),
+            game("10", "/second"),
+            game("30", "/second"),
+        ];
+        dedup_games(&mut games);
+        let games = games
+            .iter()
+            .map(|game| (game.appid.as_str(), game.library.to_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            games,
+            [("10", "/first"), ("20", "/first"), ("30", "/second")]
+        );
+    }
+
+    #[test]
+    fn excluded_ids_leave_the_pool() {
+        let opts = Opts::parse_from([
+            "steam_randomiser",
+            "--exclude-id",
+            "10",
+            "--exclude-id",
+            "30",
+        ]);
+        let filter = GameFilter {
+            opts: &opts,
+            steam_root: Path::new(""),
+            user: None,
+            appinfo: &Err(VdfError::UnexpectedEof),
+            blacklist: &Blacklist::new(Vec::new()),
+            given_games: &[],
+        };
+        let mut games = vec![
+            game("10", "/library"),
+            game("20", "/library"),
+            game("30", "/library"),
+        ];
+        let mut stats = LibraryStats::new(&games);
+        filter.by_library(&mut games, &mut stats);
+        let appids = games
+            .iter()
+            .map(|game| game.appid.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(appids, ["20"]);
+    }
+
+    /// An appinfo.vdf in the oldest format Steam still reads, giving each app
+    /// the genre ids of `apps`.
+    fn appinfo_with_genres(apps: &[(u32, &str)]) -> Vec<u8> {
+        let mut data = [0x0756_4427u32.to_le_bytes(), 1u32.to_le_bytes()].concat();
+        for (appid, genre) in apps {
+            let mut info = vec![0; 40];
+            for key in ["appinfo", "common", "genres"] {
+                info.push(0);
+                info.extend(key.as_bytes());
+                info.push(0);
+            }
+            info.extend(b"\x010\x00");
+            info.extend(genre.as_bytes());
+            info.extend(b"\x00\x08\x08\x08\x08");
+            data.extend(appid.to_le_bytes());
+            data.extend((info.len() as u32).to_le_bytes());
+            data.extend(info);
+        }
+        data.extend(0u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn listed_genres_are_those_of_the_games_left() {
+        let steam_root = std::env::temp_dir().join(format!(
+            "steam_randomiser-{}-list-genres",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(steam_root.join("appcache")).unwrap();
+        // Action and RPG.
+        let appinfo = appinfo_with_genres(&[(10, "1"), (20, "3")]);
+        std::fs::write(steam_root.join("appcache/appinfo.vdf"), appinfo).unwrap();
+        let appinfo = AppInfo::read(&steam_root, &HashSet::from(["10", "20"]));
+
+        let opts = Opts::parse_from(["steam_randomiser", "--list-genres"]);
+        let filter = GameFilter {
+            opts: &opts,
+            steam_root: &steam_root,
+            user: None,
+            appinfo: &appinfo,
+            blacklist: &Blacklist::new(vec!["appid:20".to_string()]),
+            given_games: &[],
+        };
+        let games = vec![game("10", "/library"), game("20", "/library")];
+        let genres = filter.apply(games).unwrap().genres.unwrap();
+        assert_eq!(
+            genres,
+            HashMap::from([("10".to_string(), vec!["Action".to_string()])])
+        );
+    }
+}
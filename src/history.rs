@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use steam_randomiser::error::Error;
+
+const HISTORY_FILE: &str = "steam_randomiser/history.json";
+
+/// A game that was launched by the randomiser.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub appid: String,
+    pub name: String,
+    /// Unix time of the launch.
+    pub timestamp: u64,
+}
+
+/// Location of the history file, in the user's data directory.
+pub fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// Read the launch history, oldest first. A missing history is empty, and so
+/// is a corrupted one after warning about it.
+pub fn load_history(path: &Path) -> Vec<HistoryEntry> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    match serde_json::from_str(&contents) {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!(
+                "Warning: ignoring corrupted history {}: {}",
+                path.display(),
+                err
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Write the whole history, see `write_atomic`.
+pub fn save_history(path: &Path, history: &[HistoryEntry]) -> std::io::Result<()> {
+    steam_randomiser::write_atomic(path, serde_json::to_string_pretty(history)?.as_bytes())
+}
+
+/// Add a launched game at the end of the history.
+pub fn append_history(path: &Path, entry: HistoryEntry) -> std::io::Result<()> {
+    let mut history = load_history(path);
+    history.push(entry);
+    save_history(path, &history)
+}
+
+/// Look at or forget the games launched so far.
+#[derive(clap::Subcommand)]
+pub enum HistoryCommand {
+    /// Print the launched games, oldest first.
+    Show {
+        /// Only show the last N launched games.
+        #[clap(long, value_name = "N")]
+        last: Option<usize>,
+        /// Print the history as JSON.
+        #[clap(long)]
+        json: bool,
+    },
+    /// Forget every launched game.
+    Clear {
+        /// Don't ask for confirmation.
+        #[clap(short, long)]
+        yes: bool,
+    },
+}
+
+/// Run a `history` subcommand. Only local files are used, Steam doesn't need
+/// to be installed.
+pub fn run_history_command(command: HistoryCommand) -> Result<(), Error> {
+    let path = history_path().ok_or_else(|| {
+        Error::Other("couldn't find a data directory to keep the history in".to_string())
+    })?;
+
+    match command {
+        HistoryCommand::Show { last, json } => {
+            let history = load_history(&path);
+            let skip = history.len().saturating_sub(last.unwrap_or(history.len()));
+            let history = &history[skip..];
+            if json {
+                println!("{}", serde_json::to_string(history).unwrap());
+            } else {
+                for entry in history {
+                    println!(
+                        "{}\t{}\t{}",
+                        format_timestamp(entry.timestamp),
+                        entry.appid,
+                        entry.name
+                    );
+                }
+            }
+        }
+        HistoryCommand::Clear { yes } => {
+            if !yes {
+                eprint!("Clear the history in {}? [y/N] ", path.display());
+                let mut answer = String::new();
+                let _ = std::io::stdin().read_line(&mut answer);
+                if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                    return Ok(());
+                }
+            }
+            save_history(&path, &[]).map_err(|err| {
+                Error::Other(format!(
+                    "couldn't clear history {}: {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Format unix time as a UTC `YYYY-MM-DD HH:MM` date.
+fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let minutes = timestamp % 86400 / 60;
+
+    // Civil date from days since the epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
@@ -0,0 +1,981 @@
+//! Finding Steam and the games in its libraries, and reading what Steam
+//! knows about them.
+
+pub mod appinfo;
+pub mod apptype;
+mod binary_vdf;
+mod cache;
+pub mod collections;
+pub mod error;
+pub mod owned;
+pub mod store;
+pub mod userdata;
+pub mod vdf;
+
+use cache::scan_libraries;
+use error::Error;
+use serde::{Deserialize, Serialize};
+use vdf::{parse_vdf, VdfError, VdfValue};
+
+#[cfg(target_os = "linux")]
+use std::process::{Command, Stdio};
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(target_os = "linux")]
+const FLATPAK_APPLICATIONS_PATH: &str = ".var/app/com.valvesoftware.Steam/data/Steam";
+#[cfg(target_os = "linux")]
+pub const FLATPAK_APP_ID: &str = "com.valvesoftware.Steam";
+#[cfg(target_os = "linux")]
+const SNAP_APPLICATIONS_PATH: &str = "snap/steam/common/.local/share/Steam";
+#[cfg(target_os = "linux")]
+const SNAP_BINARY: &str = "/snap/bin/steam";
+#[cfg(target_os = "linux")]
+const VANILLA_APPLICATIONS_PATHS: [&str; 3] = [
+    r#".local/share/steam"#,
+    r#".local/share/Steam"#,
+    r#".steam/steam"#,
+];
+/// Where the OS describes itself, relative to the root.
+#[cfg(target_os = "linux")]
+const OS_RELEASE_FILE: &str = "etc/os-release";
+/// Folder of Steam in `XDG_DATA_HOME`, like in the default `.local/share`.
+#[cfg(target_os = "linux")]
+const XDG_STEAM_DIR: &str = "steam";
+#[cfg(target_os = "windows")]
+const VANILLA_APPLICATIONS_PATH: &str = r#"C:\Program Files (x86)\Steam"#;
+#[cfg(target_os = "macos")]
+const VANILLA_APPLICATIONS_PATH: &str = r#"Library/Application Support/Steam"#;
+#[cfg(target_os = "macos")]
+const STEAM_APP_BUNDLE: &str = "Applications/Steam.app";
+
+/// Where a library keeps the manifests of its games.
+pub const MANIFEST_DIR: &str = "steamapps/";
+
+/// Where games are installed in a library, under their `installdir`.
+const COMMON_DIR: &str = "steamapps/common";
+
+/// `StateFlags` bit set in appmanifests once a game is completely installed.
+pub const STATE_FULLY_INSTALLED: u64 = 4;
+
+/// `StateFlags` bits of an update in progress or an uninstall, during which
+/// Steam won't launch the game even if it was fully installed.
+const STATE_BUSY: u64 = STATE_UPDATE_STARTED | STATE_UNINSTALLING;
+const STATE_UPDATE_STARTED: u64 = 1024;
+const STATE_UNINSTALLING: u64 = 2048;
+
+/// Find other install directories which are not the default one. A library
+/// list that can't be read is an error rather than libraries left out.
+fn get_other_install_dirs(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let path = path.join("libraryfolders.vdf");
+
+    let folders = match parse_vdf(&path) {
+        Ok(folders) => folders,
+        // No library list, only the default library will be scanned.
+        Err(VdfError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        }
+        Err(VdfError::Io(source)) => return Err(Error::Io { path, source }),
+        Err(source) => return Err(Error::Vdf { path, source }),
+    };
+
+    let entries = match folders.get("libraryfolders") {
+        Some(VdfValue::Block(entries)) => entries,
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(entries
+        .iter()
+        // Libraries are numbered, other keys are stats and such.
+        .filter(|(key, _)| key.parse::<u32>().is_ok())
+        .filter_map(|(_, library)| match library {
+            // Older clients only store the path
+            VdfValue::Str(path) => Some(path.as_str()),
+            VdfValue::Block(_) => library.get("path").and_then(VdfValue::as_str),
+        })
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// An installed game found in one of the Steam libraries.
+///
+/// The serialized form is part of `--output json`, so only add fields to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Game {
+    pub name: String,
+    pub appid: String,
+    /// Root of the Steam library the game is installed in.
+    pub library: PathBuf,
+    /// Unix time the game was last played, 0 if it never was.
+    #[serde(skip)]
+    pub last_played: u64,
+    /// Install state bit flags from the manifest, see `STATE_FULLY_INSTALLED`.
+    #[serde(skip)]
+    pub state_flags: u64,
+    /// Bytes used by the install, 0 if unknown.
+    #[serde(skip)]
+    pub size_on_disk: u64,
+    /// Minutes played according to the local user data, if it was read.
+    #[serde(skip)]
+    pub playtime: Option<u64>,
+    /// Folder of the game in the library's `COMMON_DIR`, empty if unknown.
+    #[serde(skip)]
+    pub install_dir: String,
+}
+
+impl Game {
+    /// Whether Steam can launch the game right away. Only needing an update
+    /// is fine, Steam updates it on launch.
+    pub fn is_fully_installed(&self) -> bool {
+        self.state_flags & STATE_FULLY_INSTALLED != 0 && self.state_flags & STATE_BUSY == 0
+    }
+
+    /// Whether the game was played in the `days` days before the unix time
+    /// `now`. A last played time of 0 means never played.
+    pub fn played_within(&self, days: u64, now: u64) -> bool {
+        let cutoff = now.saturating_sub(days * 24 * 60 * 60);
+        self.last_played != 0 && self.last_played >= cutoff
+    }
+
+    /// Whether the game was never played, as far as the user data tells.
+    pub fn is_unplayed(&self) -> bool {
+        self.playtime.unwrap_or(0) == 0
+    }
+
+    /// Whether the install is between `min_size` and `max_size` bytes. Games
+    /// of unknown size only fit if not `strict`.
+    pub fn fits_size(&self, min_size: u64, max_size: u64, strict: bool) -> bool {
+        match self.size_on_disk {
+            0 => !strict,
+            size => min_size <= size && size <= max_size,
+        }
+    }
+
+    /// Folder the game is installed in, if the manifest says.
+    pub fn install_path(&self) -> Option<PathBuf> {
+        match self.install_dir.is_empty() {
+            true => None,
+            false => Some(self.library.join(COMMON_DIR).join(&self.install_dir)),
+        }
+    }
+
+    /// Whether the game's files are still there, manifests outlive install
+    /// folders that were deleted by hand. Assumed when the folder is unknown.
+    pub fn install_dir_exists(&self) -> bool {
+        self.install_path().is_none_or(|path| path.is_dir())
+    }
+}
+
+/// Paths of the game manifests in the library.
+fn manifest_files(library: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(std::fs::read_dir(library.join(MANIFEST_DIR))?
+        .filter_map(Result::ok)
+        .filter(|file| {
+            file.file_name()
+                .to_string_lossy()
+                .starts_with("appmanifest")
+        })
+        .map(|file| file.path())
+        .collect())
+}
+
+/// How many game manifests the library has.
+pub fn count_manifests(library: &Path) -> std::io::Result<usize> {
+    Ok(manifest_files(library)?.len())
+}
+
+/// Like `parse_manifest`, but skipping a broken manifest with a warning.
+fn load_manifest(path: &Path, library: &Path) -> Option<Game> {
+    match parse_manifest(path, library) {
+        Ok(game) => Some(game),
+        Err(err) => {
+            // sometimes manifest files are empty or corrupted, skip them
+            eprintln!("Skipping manifest {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Read a game out of an appmanifest belonging to `library`.
+fn parse_manifest(path: &Path, library: &Path) -> Result<Game, VdfError> {
+    let manifest = parse_vdf(path)?;
+    let field = |key: &str| {
+        manifest
+            .lookup(&["AppState", key])
+            .and_then(VdfValue::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| VdfError::MissingKey(format!("AppState/{}", key)))
+    };
+    let number = |key: &str| field(key).ok().and_then(|t| t.parse().ok());
+    Ok(Game {
+        // Steam's app info might know the name, see `fill_missing_names`.
+        name: field("name").unwrap_or_default(),
+        appid: field("appid")?,
+        library: library.to_path_buf(),
+        last_played: number("LastPlayed").unwrap_or(0),
+        // Assume a manifest without flags is for an installed game rather
+        // than hiding it.
+        state_flags: number("StateFlags").unwrap_or(STATE_FULLY_INSTALLED),
+        size_on_disk: number("SizeOnDisk").unwrap_or(0),
+        playtime: None,
+        install_dir: field("installdir").unwrap_or_default(),
+    })
+}
+
+/// A Steam install, by how it was installed.
+#[derive(Debug, PartialEq)]
+pub enum SteamKind {
+    Vanilla,
+    AltPath(PathBuf),
+    #[cfg(target_os = "linux")]
+    Flatpak,
+    #[cfg(target_os = "linux")]
+    Snap,
+    /// No Steam to launch games with, its directory can still be read.
+    NotFound,
+}
+
+/// Environment variable pointing at a Steam install that detection can't
+/// find, like a portable one.
+pub const STEAM_ROOT_VAR: &str = "STEAM_ROOT";
+
+/// Everything Steam detection looks at, so it can be pointed at something
+/// other than the real system.
+struct SearchEnv {
+    /// The Steam directory from `STEAM_ROOT`, used instead of detecting one.
+    steam_root: Option<PathBuf>,
+    /// Directories searched for a `steam` binary, like `PATH`.
+    #[cfg(not(target_os = "windows"))]
+    path: Option<std::ffi::OsString>,
+    #[cfg(not(target_os = "windows"))]
+    home: Option<PathBuf>,
+    /// Where system wide locations like `/snap/bin` are relative to.
+    #[cfg(not(target_os = "windows"))]
+    root: PathBuf,
+    #[cfg(target_os = "linux")]
+    has_flatpak_steam: bool,
+    /// The default Steam install folder.
+    #[cfg(target_os = "windows")]
+    vanilla_dir: PathBuf,
+    /// The Steam install folder according to the registry.
+    #[cfg(target_os = "windows")]
+    registry_install_path: std::io::Result<String>,
+}
+
+impl SearchEnv {
+    /// The `STEAM_ROOT` to use when looking for `kind`. It stands in for
+    /// detection, so a kind asked for by name is looked for anyway.
+    fn steam_root_for(&self, kind: SteamKindArg) -> Option<&Path> {
+        let root = self.steam_root.as_deref()?;
+        match kind.name() {
+            None => Some(root),
+            Some(name) => {
+                eprintln!(
+                    "Ignoring {} since {} Steam was asked for",
+                    STEAM_ROOT_VAR, name
+                );
+                None
+            }
+        }
+    }
+
+    /// The environment this process runs in.
+    #[cfg(target_os = "linux")]
+    fn current() -> Self {
+        SearchEnv {
+            steam_root: std::env::var_os(STEAM_ROOT_VAR).map(PathBuf::from),
+            path: std::env::var_os("PATH"),
+            home: dirs::home_dir(),
+            root: PathBuf::from("/"),
+            has_flatpak_steam: has_flatpak_steam(),
+        }
+    }
+
+    /// The environment this process runs in.
+    #[cfg(target_os = "macos")]
+    fn current() -> Self {
+        SearchEnv {
+            steam_root: std::env::var_os(STEAM_ROOT_VAR).map(PathBuf::from),
+            path: std::env::var_os("PATH"),
+            home: dirs::home_dir(),
+            root: PathBuf::from("/"),
+        }
+    }
+
+    /// The environment this process runs in.
+    #[cfg(target_os = "windows")]
+    fn current() -> Self {
+        SearchEnv {
+            steam_root: std::env::var_os(STEAM_ROOT_VAR).map(PathBuf::from),
+            vanilla_dir: PathBuf::from(VANILLA_APPLICATIONS_PATH),
+            registry_install_path: get_steam_exe_path_from_reg(),
+        }
+    }
+
+    /// Whether a `steam` binary is on the search path.
+    #[cfg(not(target_os = "windows"))]
+    fn has_steam_binary(&self) -> bool {
+        which::which_in("steam", self.path.as_ref(), &self.root).is_ok()
+    }
+
+    /// Whether this is SteamOS, like on the Steam Deck, where Steam is part
+    /// of the system even if `steam` isn't on the search path.
+    #[cfg(target_os = "linux")]
+    fn is_steamos(&self) -> bool {
+        std::fs::read_to_string(self.root.join(OS_RELEASE_FILE))
+            .map(|release| {
+                release
+                    .lines()
+                    .any(|line| line.trim() == "ID=steamos" || line.trim() == "ID=\"steamos\"")
+            })
+            .unwrap_or(false)
+    }
+
+    /// Whether the Steam snap is installed.
+    #[cfg(target_os = "linux")]
+    fn has_snap_steam(&self) -> bool {
+        self.root.join(SNAP_BINARY.trim_start_matches('/')).exists()
+            || self
+                .home_join(SNAP_APPLICATIONS_PATH)
+                .is_some_and(|path| path.is_dir())
+    }
+
+    /// `path` under the home directory, if there is one.
+    #[cfg(not(target_os = "windows"))]
+    fn home_join(&self, path: &str) -> Option<PathBuf> {
+        self.home.as_ref().map(|home| home.join(path))
+    }
+}
+
+/// Whether the Steam flatpak is installed. Without flatpak there's none.
+#[cfg(target_os = "linux")]
+fn has_flatpak_steam() -> bool {
+    let output = Command::new("flatpak")
+        .args(["list", "--app", "--columns=application"])
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|app| app.trim() == FLATPAK_APP_ID),
+        Err(_) => false,
+    }
+}
+
+impl SteamKind {
+    /// Detect which Steam is installed in `env`. A native install wins over
+    /// the flatpak, which wins over the snap.
+    #[cfg(target_os = "linux")]
+    fn detect(env: &SearchEnv) -> Result<Self, Error> {
+        Ok(
+            match (
+                env.has_steam_binary() || env.is_steamos(),
+                env.has_flatpak_steam,
+                env.has_snap_steam(),
+            ) {
+                (true, _, _) => SteamKind::Vanilla,
+                (_, true, _) => SteamKind::Flatpak,
+                (_, _, true) => SteamKind::Snap,
+                _ => return Err(Error::SteamNotFound(None)),
+            },
+        )
+    }
+
+    /// Detect which Steam is installed in `env`, the default install folder
+    /// first and then the one from the registry.
+    #[cfg(target_os = "windows")]
+    fn detect(env: &SearchEnv) -> Result<Self, Error> {
+        if env.vanilla_dir.join("steam.exe").is_file() {
+            return Ok(SteamKind::Vanilla);
+        }
+        match &env.registry_install_path {
+            Ok(binary_path) => {
+                if Path::new(binary_path).join("steam.exe").is_file() {
+                    Ok(SteamKind::AltPath(binary_path.into()))
+                } else {
+                    eprintln!("steam.exe was not in install folder");
+                    eprintln!("expected path according to registry: {:?}", binary_path);
+                    Err(Error::SteamNotFound(None))
+                }
+            }
+            Err(err) => {
+                eprintln!("Couldn't find steam in registry due to error: {}", err);
+                Err(Error::SteamNotFound(None))
+            }
+        }
+    }
+
+    /// Detect whether Steam is installed in `env`.
+    #[cfg(target_os = "macos")]
+    fn detect(env: &SearchEnv) -> Result<Self, Error> {
+        // Steam.app doesn't put a `steam` binary on the PATH, so also look for the
+        // app bundle (system wide or per user) and Steam's data directory.
+        let has_steam_vanilla = env.has_steam_binary()
+            || env.root.join(STEAM_APP_BUNDLE).is_dir()
+            || [STEAM_APP_BUNDLE, VANILLA_APPLICATIONS_PATH]
+                .iter()
+                .any(|path| env.home_join(path).is_some_and(|path| path.is_dir()));
+        match has_steam_vanilla {
+            true => Ok(SteamKind::Vanilla),
+            _ => Err(Error::SteamNotFound(None)),
+        }
+    }
+
+    /// Find the Steam install asked for in `env`. A `STEAM_ROOT` is only
+    /// used when any kind will do.
+    #[cfg(target_os = "linux")]
+    fn find(env: &SearchEnv, kind: SteamKindArg) -> Result<Self, Error> {
+        if let Some(root) = env.steam_root_for(kind) {
+            check_steam_dir(root)?;
+            return Ok(SteamKind::AltPath(root.to_path_buf()));
+        }
+        let (found, steam_kind) = match kind {
+            SteamKindArg::Auto => return SteamKind::detect(env),
+            SteamKindArg::Vanilla => (
+                env.has_steam_binary() || env.is_steamos(),
+                SteamKind::Vanilla,
+            ),
+            SteamKindArg::Flatpak => (env.has_flatpak_steam, SteamKind::Flatpak),
+            SteamKindArg::Snap => (env.has_snap_steam(), SteamKind::Snap),
+        };
+        match found {
+            true => Ok(steam_kind),
+            false => Err(Error::SteamNotFound(kind.name())),
+        }
+    }
+
+    /// Find the Steam install asked for in `env`. There is only the native
+    /// one here, unless there's a `STEAM_ROOT` and any kind will do.
+    #[cfg(not(target_os = "linux"))]
+    fn find(env: &SearchEnv, kind: SteamKindArg) -> Result<Self, Error> {
+        if let Some(root) = env.steam_root_for(kind) {
+            check_steam_dir(root)?;
+            return Ok(SteamKind::AltPath(root.to_path_buf()));
+        }
+        match kind {
+            SteamKindArg::Auto | SteamKindArg::Vanilla => SteamKind::detect(env),
+        }
+    }
+}
+
+/// Find a Steam directory on this machine, whether or not Steam itself can
+/// be found to launch games.
+pub fn find_steam_dir() -> Option<PathBuf> {
+    let home = dirs::home_dir().unwrap_or_default();
+    #[cfg(target_os = "linux")]
+    let candidates = {
+        let mut candidates = vanilla_dirs(&home);
+        candidates.push(home.join(FLATPAK_APPLICATIONS_PATH));
+        candidates.push(home.join(SNAP_APPLICATIONS_PATH));
+        candidates
+    };
+    #[cfg(not(target_os = "linux"))]
+    let candidates = [home.join(VANILLA_APPLICATIONS_PATH)];
+    candidates
+        .into_iter()
+        .find(|dir| dir.join(MANIFEST_DIR).is_dir())
+}
+
+/// Directories a native Steam install can be in, the one in the XDG data
+/// directory first when it isn't the default one.
+#[cfg(target_os = "linux")]
+fn vanilla_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        // The spec says to ignore relative paths.
+        .filter(|data_home| data_home.is_absolute())
+        .map(|data_home| vec![data_home.join(XDG_STEAM_DIR)])
+        .unwrap_or_default();
+    dirs.extend(
+        VANILLA_APPLICATIONS_PATHS
+            .iter()
+            .map(|path| home.join(path)),
+    );
+    dirs
+}
+
+/// The directory of a Steam install, where its default library is.
+pub fn steam_dir(steam_type: &SteamKind) -> Result<PathBuf, Error> {
+    let mut home = dirs::home_dir().ok_or(Error::NoHomeDir)?;
+    match steam_type {
+        #[cfg(target_os = "linux")]
+        SteamKind::Flatpak => home.push(FLATPAK_APPLICATIONS_PATH),
+        #[cfg(target_os = "linux")]
+        SteamKind::Snap => home.push(SNAP_APPLICATIONS_PATH),
+        #[cfg(target_os = "linux")]
+        SteamKind::Vanilla => {
+            home = vanilla_dirs(&home)
+                .into_iter()
+                .find(|dir| dir.is_dir())
+                .ok_or(Error::SteamNotFound(None))?
+        }
+        #[cfg(not(target_os = "linux"))]
+        SteamKind::Vanilla => home.push(VANILLA_APPLICATIONS_PATH),
+        SteamKind::AltPath(path) => home = path.clone(),
+        _ => return Err(Error::SteamNotFound(None)),
+    }
+    Ok(home)
+}
+
+/// Check that `root` is a Steam directory, with a folder of game manifests.
+pub fn check_steam_dir(root: &Path) -> Result<(), Error> {
+    match root.join(MANIFEST_DIR).is_dir() {
+        true => Ok(()),
+        false => Err(Error::NotSteamDir(root.to_path_buf())),
+    }
+}
+
+/// Every library of the Steam directory `steam_root`, and `extra_libraries`,
+/// whether or not they're there.
+pub fn library_dirs(steam_root: &Path, extra_libraries: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+    let other_libraries = get_other_install_dirs(&steam_root.join(MANIFEST_DIR))?;
+    for library in other_libraries.iter().chain(extra_libraries) {
+        if !libraries.contains(library) {
+            libraries.push(library.clone());
+        }
+    }
+    Ok(libraries)
+}
+
+/// Find the games of every library of the Steam install `steam`.
+pub fn discover_games(steam: &SteamKind) -> Result<Vec<Game>, Error> {
+    discover_games_in(&steam_dir(steam)?, &[], false)
+}
+
+/// Find the games of every library of the Steam directory `steam_root`, and
+/// of `extra_libraries`. Games moved between libraries can show up twice.
+/// The games of libraries that didn't change are taken from the cache,
+/// unless `refresh`.
+///
+/// Libraries on drives that aren't mounted, like a Steam Deck's SD card
+/// that was taken out, are skipped.
+pub fn discover_games_in(
+    steam_root: &Path,
+    extra_libraries: &[PathBuf],
+    refresh: bool,
+) -> Result<Vec<Game>, Error> {
+    check_steam_dir(steam_root)?;
+    let mut libraries = library_dirs(steam_root, extra_libraries)?;
+    libraries.retain(|library| library.is_dir());
+    Ok(scan_libraries(&libraries, refresh))
+}
+
+/// Detect if Steam is installed, or only the kind of install given.
+pub fn detect_steam(kind: SteamKindArg) -> Result<SteamKind, Error> {
+    SteamKind::find(&SearchEnv::current(), kind)
+}
+
+#[cfg(target_os = "windows")]
+/// Attempt to find steam's install location via the windows registry
+fn get_steam_exe_path_from_reg() -> std::io::Result<String> {
+    use winreg::enums::*;
+    let read = |root, key: &str, value: &str| {
+        winreg::RegKey::predef(root)
+            .open_subkey(key)
+            .and_then(|steam| steam.get_value::<String, _>(value))
+    };
+    let path = match read(
+        HKEY_LOCAL_MACHINE,
+        r#"SOFTWARE\WOW6432Node\Valve\Steam"#,
+        "InstallPath",
+    ) {
+        Ok(path) => path,
+        // Installs made without admin rights are only in the user's registry.
+        Err(machine_err) => read(HKEY_CURRENT_USER, r#"Software\Valve\Steam"#, "SteamPath")
+            .map_err(|user_err| {
+                std::io::Error::new(
+                    user_err.kind(),
+                    format!(
+                        r#"tried HKLM\SOFTWARE\WOW6432Node\Valve\Steam\InstallPath ({}) and HKCU\Software\Valve\Steam\SteamPath ({})"#,
+                        machine_err, user_err
+                    ),
+                )
+            })?,
+    };
+    // SteamPath uses forward slashes.
+    Ok(path.replace('/', "\\"))
+}
+
+/// Steam installs that can be asked for with `--steam-kind`.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SteamKindArg {
+    Auto,
+    Vanilla,
+    Flatpak,
+    Snap,
+}
+
+/// Steam installs that can be asked for with `--steam-kind`.
+#[cfg(not(target_os = "linux"))]
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SteamKindArg {
+    Auto,
+    Vanilla,
+}
+
+impl SteamKindArg {
+    /// The name of the kind of install asked for, `None` for any.
+    pub fn name(self) -> Option<String> {
+        use clap::ValueEnum;
+        match self {
+            SteamKindArg::Auto => None,
+            kind => kind
+                .to_possible_value()
+                .map(|kind| kind.get_name().to_string()),
+        }
+    }
+}
+
+/// Write `bytes` to `path`, going through a temporary file so an interrupted
+/// write can't corrupt it. Creates the parent directory if needed.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+/// Current time in unix seconds.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for the test `name`.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("steam_randomiser-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write an appmanifest for `appid` into the library `library`, with
+    /// `fields` inside its `AppState`.
+    fn write_manifest(library: &Path, appid: &str, fields: &str) -> PathBuf {
+        let dir = library.join(MANIFEST_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("appmanifest_{}.acf", appid));
+        let manifest = format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\t\"{}\"\n{}}}\n",
+            appid, fields
+        );
+        std::fs::write(&path, manifest).unwrap();
+        path
+    }
+
+    #[test]
+    fn only_fully_installed_games_that_are_not_busy_count() {
+        let library = test_dir("state-flags");
+        // Installed, installed but needing an update, downloading for the
+        // first time, and an update that was paused.
+        for (flags, installed) in [(4, true), (6, true), (1026, false), (1542, false)] {
+            let fields = format!(
+                "\t\"name\"\t\t\"Game\"\n\t\"StateFlags\"\t\t\"{}\"\n",
+                flags
+            );
+            let path = write_manifest(&library, &flags.to_string(), &fields);
+            let game = parse_manifest(&path, &library).unwrap();
+            assert_eq!(game.state_flags, flags);
+            assert_eq!(game.is_fully_installed(), installed, "StateFlags {}", flags);
+        }
+    }
+
+    #[test]
+    fn played_within_counts_days_back_from_now() {
+        // 2024-01-31 00:00 UTC.
+        let now = 1_706_659_200;
+        let day = 24 * 60 * 60;
+        let played = |last_played| Game {
+            name: "Game".to_string(),
+            appid: "1".to_string(),
+            library: PathBuf::new(),
+            last_played,
+            state_flags: STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime: None,
+            install_dir: String::new(),
+        };
+        assert!(played(now - day).played_within(7, now));
+        assert!(played(now - 7 * day).played_within(7, now));
+        assert!(!played(now - 7 * day - 1).played_within(7, now));
+        assert!(!played(now - 30 * day).played_within(7, now));
+        // Never played.
+        assert!(!played(0).played_within(7, now));
+        assert!(!played(0).played_within(100_000, now));
+    }
+
+    #[test]
+    fn reads_a_manifest_without_a_name() {
+        let library = test_dir("manifest-without-name");
+        let path = write_manifest(&library, "620", "\t\"StateFlags\"\t\t\"4\"\n");
+        let game = parse_manifest(&path, &library).unwrap();
+        assert_eq!(game.appid, "620");
+        assert_eq!(game.name, "");
+    }
+
+    #[test]
+    fn an_empty_manifest_does_not_stop_the_scan() {
+        let library = test_dir("empty-manifest");
+        std::fs::write(write_manifest(&library, "1", ""), "").unwrap();
+        write_manifest(&library, "2", "\t\"name\"\t\t\"Two\"\n");
+        write_manifest(&library, "3", "\t\"name\"\t\t\"Three\"\n");
+        let mut appids = manifest_files(&library)
+            .unwrap()
+            .iter()
+            .filter_map(|path| load_manifest(path, &library))
+            .map(|game| game.appid)
+            .collect::<Vec<_>>();
+        appids.sort();
+        assert_eq!(appids, ["2", "3"]);
+    }
+
+    /// The libraries listed by a libraryfolders.vdf holding `libraryfolders`.
+    fn other_install_dirs(libraryfolders: &str, name: &str) -> Vec<PathBuf> {
+        let steamapps = test_dir(name).join(MANIFEST_DIR);
+        std::fs::create_dir_all(&steamapps).unwrap();
+        std::fs::write(steamapps.join("libraryfolders.vdf"), libraryfolders).unwrap();
+        get_other_install_dirs(&steamapps).unwrap()
+    }
+
+    #[test]
+    fn reads_linux_libraryfolders() {
+        let libraryfolders = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/deck/.local/share/Steam"
+		"label"		""
+		"contentid"		"2398415396489456123"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"29212173"
+		}
+	}
+	"1"
+	{
+		"path"		"/run/media/deck/SD Card/Steam Library"
+		"label"		"SD card"
+		"apps"
+		{
+		}
+	}
+}
+"#;
+        assert_eq!(
+            other_install_dirs(libraryfolders, "libraryfolders-linux"),
+            [
+                PathBuf::from("/home/deck/.local/share/Steam"),
+                PathBuf::from("/run/media/deck/SD Card/Steam Library"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_windows_libraryfolders() {
+        let libraryfolders = r#""libraryfolders"
+{
+	"contentstatsid"		"-4387318843127567424"
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+		"label"		""
+	}
+}
+"#;
+        assert_eq!(
+            other_install_dirs(libraryfolders, "libraryfolders-windows"),
+            [
+                PathBuf::from(r"C:\Program Files (x86)\Steam"),
+                PathBuf::from(r"D:\SteamLibrary"),
+            ]
+        );
+    }
+
+    #[test]
+    fn corrupt_libraryfolders_is_an_error() {
+        let steamapps = test_dir("libraryfolders-corrupt").join(MANIFEST_DIR);
+        std::fs::create_dir_all(&steamapps).unwrap();
+        std::fs::write(
+            steamapps.join("libraryfolders.vdf"),
+            "\"libraryfolders\"\n{\n\t\"0\"\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            get_other_install_dirs(&steamapps),
+            Err(Error::Vdf { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_libraryfolders_is_no_other_library() {
+        let steamapps = test_dir("libraryfolders-missing").join(MANIFEST_DIR);
+        assert!(get_other_install_dirs(&steamapps).unwrap().is_empty());
+    }
+
+    /// A system with nothing installed, rooted at `dir`.
+    #[cfg(target_os = "linux")]
+    fn empty_env(dir: &Path) -> SearchEnv {
+        SearchEnv {
+            steam_root: None,
+            path: Some(dir.join("bin").into_os_string()),
+            home: Some(dir.join("home")),
+            root: dir.to_path_buf(),
+            has_flatpak_steam: false,
+        }
+    }
+
+    /// A system with nothing installed, rooted at `dir`.
+    #[cfg(target_os = "macos")]
+    fn empty_env(dir: &Path) -> SearchEnv {
+        SearchEnv {
+            steam_root: None,
+            path: Some(dir.join("bin").into_os_string()),
+            home: Some(dir.join("home")),
+            root: dir.to_path_buf(),
+        }
+    }
+
+    /// A system with nothing installed, rooted at `dir`.
+    #[cfg(target_os = "windows")]
+    fn empty_env(dir: &Path) -> SearchEnv {
+        SearchEnv {
+            steam_root: None,
+            vanilla_dir: dir.join("Steam"),
+            registry_install_path: Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    #[test]
+    fn detects_nothing_on_an_empty_system() {
+        let env = empty_env(&test_dir("detect-none"));
+        assert!(matches!(
+            SteamKind::find(&env, SteamKindArg::Auto),
+            Err(Error::SteamNotFound(None))
+        ));
+    }
+
+    #[test]
+    fn steam_root_is_used_as_is() {
+        let dir = test_dir("detect-steam-root");
+        std::fs::create_dir_all(dir.join("portable").join(MANIFEST_DIR)).unwrap();
+        let env = SearchEnv {
+            steam_root: Some(dir.join("portable")),
+            ..empty_env(&dir)
+        };
+        assert_eq!(
+            SteamKind::find(&env, SteamKindArg::Auto).unwrap(),
+            SteamKind::AltPath(dir.join("portable"))
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn steam_root_does_not_replace_the_kind_asked_for() {
+        let dir = test_dir("detect-steam-root-flatpak");
+        std::fs::create_dir_all(dir.join("portable").join(MANIFEST_DIR)).unwrap();
+        let env = SearchEnv {
+            steam_root: Some(dir.join("portable")),
+            has_flatpak_steam: true,
+            ..empty_env(&dir)
+        };
+        assert_eq!(
+            SteamKind::find(&env, SteamKindArg::Flatpak).unwrap(),
+            SteamKind::Flatpak
+        );
+    }
+
+    #[test]
+    fn steam_root_without_libraries_is_an_error() {
+        let dir = test_dir("detect-bad-steam-root");
+        let env = SearchEnv {
+            steam_root: Some(dir.join("portable")),
+            ..empty_env(&dir)
+        };
+        assert!(matches!(
+            SteamKind::find(&env, SteamKindArg::Auto),
+            Err(Error::NotSteamDir(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn detects_flatpak() {
+        let env = SearchEnv {
+            has_flatpak_steam: true,
+            ..empty_env(&test_dir("detect-flatpak"))
+        };
+        assert_eq!(
+            SteamKind::find(&env, SteamKindArg::Auto).unwrap(),
+            SteamKind::Flatpak
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn detects_snap() {
+        let dir = test_dir("detect-snap");
+        std::fs::create_dir_all(dir.join("home").join(SNAP_APPLICATIONS_PATH)).unwrap();
+        assert_eq!(
+            SteamKind::find(&empty_env(&dir), SteamKindArg::Auto).unwrap(),
+            SteamKind::Snap
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn native_steam_wins_over_flatpak_and_snap() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = test_dir("detect-vanilla");
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(dir.join("bin/steam"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(dir.join("bin/steam"), PermissionsExt::from_mode(0o755)).unwrap();
+        std::fs::create_dir_all(dir.join("home").join(SNAP_APPLICATIONS_PATH)).unwrap();
+        let env = SearchEnv {
+            has_flatpak_steam: true,
+            ..empty_env(&dir)
+        };
+        assert_eq!(
+            SteamKind::find(&env, SteamKindArg::Auto).unwrap(),
+            SteamKind::Vanilla
+        );
+        assert_eq!(
+            SteamKind::find(&env, SteamKindArg::Flatpak).unwrap(),
+            SteamKind::Flatpak
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kind_asked_for_must_be_there() {
+        let env = SearchEnv {
+            has_flatpak_steam: true,
+            ..empty_env(&test_dir("detect-missing-kind"))
+        };
+        assert!(matches!(
+            SteamKind::find(&env, SteamKindArg::Snap),
+            Err(Error::SteamNotFound(Some(kind))) if kind == "snap"
+        ));
+    }
+}
@@ -1,372 +1,1568 @@
-use clap::Parser;
-use rand::seq::SliceRandom;
+/// Print a message meant for humans. It goes to stderr when stdout is used
+/// for machine readable output.
+macro_rules! info {
+    ($opts:expr, $($arg:tt)*) => {
+        if $opts.machine_readable() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+mod config;
+mod duration;
+mod export;
+mod filters;
+mod history;
+mod pick;
+mod running;
+mod size;
+mod stats;
+mod tui;
+
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use config::{config_path, load_config, Config};
+use duration::parse_duration;
+use export::{export_games, parse_export, Export};
+use filters::{Filtered, GameFilter};
+use history::{run_history_command, HistoryCommand};
+use pick::pick_and_launch;
+use serde::Deserialize;
+use size::{format_size, parse_size};
+#[cfg(target_os = "linux")]
+use steam_randomiser::FLATPAK_APP_ID;
+use steam_randomiser::{
+    appinfo::AppInfo,
+    check_steam_dir, count_manifests, detect_steam, discover_games_in,
+    error::Error,
+    find_steam_dir, library_dirs,
+    owned::owned_games,
+    steam_dir,
+    userdata::{read_login_users, select_user, steam_id64},
+    Game, SteamKind, SteamKindArg, MANIFEST_DIR, STATE_FULLY_INSTALLED, STEAM_ROOT_VAR,
+};
+
 use std::{
-    fs::DirEntry,
+    collections::{HashMap, HashSet},
+    io::IsTerminal,
     path::{Path, PathBuf},
-    process::{Child, Command, Stdio},
+    process::{Command, Stdio},
+    time::Duration,
 };
 
-#[cfg(target_os = "linux")]
-const FLATPAK_APPLICATIONS_PATH: &str = ".var/app/com.valvesoftware.Steam/data/Steam";
-#[cfg(target_os = "linux")]
-const VANILLA_APPLICATIONS_PATHS: [&str; 2] = [r#".local/share/steam"#, r#".steam/steam"#];
-#[cfg(target_os = "windows")]
-const VANILLA_APPLICATIONS_PATH: &str = r#"C:\Program Files (x86)\Steam"#;
-#[cfg(target_os = "macos")]
-const VANILLA_APPLICATIONS_PATH: &str = r#"Library/Application Support/Steam"#;
+/// Bold green, for the picked game.
+const HIGHLIGHT_COLOR: &str = "\x1b[1;32m";
+const RESET_COLOR: &str = "\x1b[0m";
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const MANIFEST_DIR: &str = "steamapps/";
+/// Where Steam caches the artwork of games.
+const LIBRARY_CACHE_DIR: &str = "appcache/librarycache";
 
-/// Builds the appropriate url to run the game
-fn generate_steam_rungame(id: &str) -> String {
-    format!("steam://rungameid/{}", id)
-}
+/// Steam Deck compatibility categories in Steam's app info.
+const DECK_UNSUPPORTED: u8 = 1;
+const DECK_PLAYABLE: u8 = 2;
+const DECK_VERIFIED: u8 = 3;
 
-/// Detect if app is a Proton runtime
-fn is_proton(app_name: &str) -> bool {
-    if app_name.starts_with("Proton") {
-        let number = app_name.split(' ').collect::<Vec<&str>>();
-        if number.len() > 1 {
-            let number = number[1].parse::<f32>();
-            if number.unwrap_or(0.0) != 0.0 {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-/// List of names of applications/games we don't want to launch.
-fn is_blacklisted(app_name: &str) -> bool {
-    let steam_libs = [
-        "Steamworks Common Redistributables",
-        "SteamVR",
-        "Proton Experimental",
-    ];
+/// Name of this system in the `oslist` of Steam's app info.
+#[cfg(target_os = "linux")]
+const NATIVE_PLATFORM: &str = "linux";
+#[cfg(target_os = "macos")]
+const NATIVE_PLATFORM: &str = "macos";
+#[cfg(target_os = "windows")]
+const NATIVE_PLATFORM: &str = "windows";
 
-    steam_libs.iter().any(|&b| b == app_name)
-	|| app_name.ends_with("Soundtrack") // This **should** deal with downloaded albums, and ignore them
-	|| is_proton(app_name)
-	|| app_name.starts_with("Steam Linux Runtime")
-}
+/// How many games `--never-played` falls back to when every game was played.
+const LEAST_PLAYED_FALLBACK: usize = 5;
 
-// fn parse_vdf(path_to_vdf: &Path) -> HashMap<String, String> {
-//     let mut res = HashMap::new();
+/// How many names `--spin` shows before the pick, and how long it stays on
+/// each, slowing down towards the end.
+const SPIN_STEPS: u32 = 30;
+const SPIN_FIRST_DELAY: Duration = Duration::from_millis(20);
+const SPIN_SLOWDOWN: Duration = Duration::from_millis(4);
 
-//     let contents = std::fs::read_to_string(path_to_vdf);
-//     let lines = contents.unwrap();
-//     let lines:Vec<&str> = lines.lines().collect();
+/// How many other games are tried when launching the picked one fails.
+const LAUNCH_RETRIES: usize = 3;
 
-//     for line in 0..lines.len() {
-//         println!("Working on {:?}", lines[line]);
-//         if line+1 < lines.len() && lines[line+1].trim() == "{" {
+/// How often the manifests are read again while waiting for an install.
+const INSTALL_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
-//         } else{
-//             let key_value:Vec<&str> = lines[line].split_whitespace().collect();
-//             if key_value.len() < 2 {
-//                 continue;
-//             }
-//             println!("splitted {:?}", key_value);
-//             res.insert(key_value[0].to_string(), key_value[1].to_string());
+const BLACKLIST_FILE: &str = "steam_randomiser/blacklist.txt";
 
-//         }
-//     }
+/// Store page of a game, followed by its appid.
+const STORE_PAGE_URL: &str = "https://store.steampowered.com/app/";
 
-//     res
-// }
+/// Url switching Steam to its Big Picture mode, starting Steam if needed.
+const BIG_PICTURE_URL: &str = "steam://open/bigpicture";
 
-/// Find other install directories which are not the default one
-fn get_other_install_dirs(path: &Path) -> Vec<String> {
-    let mut path = path.to_path_buf();
-    path.push("libraryfolders.vdf");
+/// Builds the appropriate url to run the game, passing it `args` if given
+fn generate_steam_rungame(id: &str, args: Option<&str>) -> String {
+    match args {
+        Some(args) => format!("steam://run/{}//{}/", id, url_encode(args)),
+        None => format!("steam://rungameid/{}", id),
+    }
+}
 
-    let path = path.as_path();
+/// Builds the url opening the game's store page in Steam.
+fn generate_steam_store(id: &str) -> String {
+    format!("steam://store/{}", id)
+}
 
-    let contents = std::fs::read_to_string(path);
-    let lines = contents.unwrap();
+/// Builds the url opening Steam's install dialog for the game.
+fn generate_steam_install(id: &str) -> String {
+    format!("steam://install/{}", id)
+}
 
-    let mut libs = Vec::new();
+/// Percent-encode everything but unreserved URL characters.
+fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
 
-    let lines = lines.lines();
-    for line in lines {
-        if line.contains("path") {
-            let splitted: Vec<&str> = line.split_whitespace().collect();
-            libs.push(splitted[1][1..splitted[1].len() - 1].to_string());
-        }
-    }
-    libs
+/// Names and app ids of applications/games we don't want to launch, from the
+/// user's blacklist file and config.
+struct Blacklist {
+    names: Vec<String>,
+    appids: Vec<String>,
 }
 
-// Parse manifest and get list of game names with their ids.
-fn get_games_from_manifest_in_path(path: &Path) -> Vec<(String, String)> {
-    let dir = {
-        match std::fs::read_dir(path) {
-            Ok(path) => path,
-            Err(_) => {
-                // sometimes steam can have a corrupted library path, this is
-                // probably fine since it only appeared for paths not in use for
-                // me. Skip library and hope this is fine.
-                return Vec::new();
+impl Blacklist {
+    /// Sort user entries, which are either a game name or `appid:<id>`.
+    fn new(entries: Vec<String>) -> Self {
+        let mut names = Vec::new();
+        let mut appids = Vec::new();
+        for entry in entries {
+            match entry.strip_prefix("appid:") {
+                Some(id) => appids.push(id.trim().to_string()),
+                None => names.push(entry),
             }
         }
-    };
+        Blacklist { names, appids }
+    }
+}
 
-    let manifest_files = dir
-        .map(|e| e.unwrap())
-        .filter(|file| {
-            let file_name = file.file_name();
-            let file_name = file_name.to_str().unwrap();
-            file_name.starts_with("appmanifest")
-        })
-        .collect::<Vec<DirEntry>>();
+/// Check if an application/game is one we don't want to launch.
+fn is_blacklisted(app_name: &str, appid: &str, blacklist: &Blacklist) -> bool {
+    blacklist.names.iter().any(|b| b == app_name) || blacklist.appids.iter().any(|b| b == appid)
+}
 
-    let mut games = Vec::new();
+/// Location of the user's blacklist file if none is given on the command line.
+fn default_blacklist_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(BLACKLIST_FILE))
+}
 
-    for file in manifest_files {
-        let file_path = file.path();
-        let contents = std::fs::read_to_string(file_path);
-        let lines = contents.unwrap();
-        let lines = lines.lines().skip(2).collect::<Vec<&str>>();
+/// Read a list of games such as a blacklist: one entry per line, `#` starts a
+/// comment line.
+fn load_game_list(path: &Path) -> std::io::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
 
-        let mut game = "".to_string();
-        let mut id = "".to_string();
+/// Case-insensitive check for `pattern` anywhere in the game name.
+fn name_matches(app_name: &str, pattern: &str) -> bool {
+    app_name.to_lowercase().contains(&pattern.to_lowercase())
+}
 
-        if lines.is_empty() {
-            // sometimes manifest files are empty or corrupted, skip them
-            return games;
-        }
+/// Games for the given appids without looking at the libraries, for
+/// launching them directly. They're named after `names`, from Steam's app
+/// info, if it has them.
+fn direct_games(
+    steam_root: Option<&Path>,
+    names: &HashMap<String, String>,
+    appids: &[&str],
+) -> Vec<Game> {
+    appids
+        .iter()
+        .map(|appid| Game {
+            name: names
+                .get(*appid)
+                .cloned()
+                .unwrap_or_else(|| appid.to_string()),
+            appid: appid.to_string(),
+            library: steam_root.map(Path::to_path_buf).unwrap_or_default(),
+            last_played: 0,
+            state_flags: STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime: None,
+            install_dir: String::new(),
+        })
+        .collect()
+}
 
-        for line in lines.iter().take(lines.len() - 1) {
-            let line = line
-                .split('\t')
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<&str>>();
-            if line[0].contains("name") {
-                let app_name = line[1].replace('\"', "");
+/// Whether `game` is the one a user list entry, a game name or an app id,
+/// is about.
+fn is_wanted(game: &Game, entry: &str) -> bool {
+    entry == game.appid || entry.eq_ignore_ascii_case(&game.name)
+}
 
-                game = app_name.clone();
+/// Name the games whose manifest has no name after Steam's app info, and drop
+/// those it doesn't know either, their manifest is probably corrupt.
+fn fill_missing_names(opts: &Opts, names: &HashMap<String, String>, games: &mut Vec<Game>) {
+    games.retain_mut(|game| {
+        if !game.name.is_empty() {
+            return true;
+        }
+        match names.get(&game.appid) {
+            Some(name) => game.name = name.clone(),
+            None if opts.verbose > 0 => {
+                info!(
+                    opts,
+                    "Skipping app {}, its manifest has no name", game.appid
+                )
             }
+            None => {}
+        }
+        !game.name.is_empty()
+    });
+}
 
-            if line[0].contains("appid") {
-                let app_id = line[1].replace('\"', "");
-                id = app_id.clone();
-            }
+/// The games `steam_id` owns for `--pool owned`, as if they were installed.
+fn load_owned_games(opts: &Opts, steam_id: Option<u64>) -> Result<Vec<Game>, Error> {
+    let (api_key, steam_id) = match (&opts.api_key, steam_id) {
+        (Some(api_key), Some(steam_id)) => (api_key, steam_id64(steam_id)),
+        (None, _) => {
+            return Err(Error::Other(
+                "--pool owned needs a Steam Web API key, see --api-key".to_string(),
+            ))
         }
-        if !is_blacklisted(&game) {
-            games.push((game, id));
+        (_, None) => {
+            return Err(Error::Other(
+                "--pool owned needs the account to pick for, see --user".to_string(),
+            ))
         }
-    }
-
-    games
+    };
+    let owned = owned_games(api_key, steam_id, opts.owned_ttl)
+        .map_err(|err| Error::Other(format!("couldn't get the owned games: {}", err)))?;
+    Ok(owned
+        .into_iter()
+        .map(|game| Game {
+            name: game.name,
+            appid: game.appid,
+            library: PathBuf::new(),
+            last_played: game.last_played,
+            state_flags: STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime: Some(game.playtime),
+            install_dir: String::new(),
+        })
+        .collect())
 }
 
-#[derive(Debug, PartialEq)]
-enum SteamKind {
-    Vanilla,
-    AltPath(PathBuf),
-    #[cfg(target_os = "linux")]
-    Flatpak,
-    NotFound,
+/// Check that `--steam-binary` is something that can be run. A bare name is
+/// looked up on the PATH.
+fn parse_steam_binary(binary: &str) -> Result<PathBuf, String> {
+    which::which(binary).map_err(|_| format!("{} isn't an executable", binary))
 }
 
-/// Detect if Steam is installed.
-#[cfg(target_os = "linux")]
-fn detect_steam() -> SteamKind {
-    let has_steam_vanilla = which::which("steam").is_ok();
-    let flatpak_list = Command::new("flatpak")
-        .arg("list")
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let mut flatpak_steam_output = Command::new("grep")
-        .stdin(flatpak_list.stdout.unwrap())
-        .arg("-c")
-        .arg("Steam")
-        .output()
-        .unwrap()
-        .stdout;
-    flatpak_steam_output.remove(flatpak_steam_output.len() - 1);
-    let has_flatpak_steam = String::from_utf8(flatpak_steam_output).unwrap();
-
-    let has_flatpak_steam = has_flatpak_steam.parse::<u32>().unwrap() > 0;
-
-    match (has_steam_vanilla, has_flatpak_steam) {
-        (true, _) => SteamKind::Vanilla,
-        (_, true) => SteamKind::Flatpak,
-        _ => SteamKind::NotFound,
+/// Check that an app id is a positive number.
+fn parse_appid(appid: &str) -> Result<String, String> {
+    match appid.parse::<u64>() {
+        Ok(id) if id > 0 => Ok(id.to_string()),
+        _ => Err(format!("{} isn't an app id", appid)),
     }
 }
 
-/// Detect if Steam is installed.
-#[cfg(target_os = "windows")]
-fn detect_steam() -> SteamKind {
-    let has_steam_vanilla = which::which(r#"C:\Program Files (x86)\Steam\steam.exe"#).is_ok();
-    if has_steam_vanilla {
-        return SteamKind::Vanilla;
-    }
-    match get_steam_exe_path_from_reg() {
-        Ok(binary_path) => {
-            if which::which(binary_path.clone() + r#"\steam.exe"#).is_ok() {
-                SteamKind::AltPath(binary_path.into())
-            } else {
-                eprintln!("steam.exe was not in install folder");
-                eprintln!("expected path according to registry: {:?}", binary_path);
-                SteamKind::NotFound
-            }
+/// Command opening the url with the given Steam executable.
+fn binary_command(binary: &Path, url: &str) -> Command {
+    let mut command = Command::new(binary);
+    command.arg(url);
+    command
+}
+
+/// Command opening the url with the appropriate Steam environment, `None`
+/// without Steam.
+#[cfg(target_os = "linux")]
+fn steam_command(steam_type: &SteamKind, url: &str) -> Option<Command> {
+    let mut command = match steam_type {
+        SteamKind::Flatpak => {
+            let mut command = Command::new("flatpak");
+            command.args(["run", FLATPAK_APP_ID]);
+            command
         }
-        Err(err) => {
-            eprintln!("Couldn't find steam in registry due to error: {}", err);
-            SteamKind::NotFound
+        SteamKind::Vanilla => Command::new("steam"),
+        SteamKind::Snap => {
+            let mut command = Command::new("snap");
+            command.args(["run", "steam"]);
+            command
         }
-    }
+        SteamKind::AltPath(path) => Command::new(path.join("steam.sh")),
+        SteamKind::NotFound => return None,
+    };
+    command.arg(url);
+    Some(command)
 }
 
+/// Command opening the url with the appropriate Steam environment, `None`
+/// without Steam.
 #[cfg(target_os = "windows")]
-/// Attempt to find steam's install location via the windows registry
-fn get_steam_exe_path_from_reg() -> std::io::Result<String> {
-    use winreg::enums::*;
-    let hklm = winreg::RegKey::predef(HKEY_LOCAL_MACHINE);
-    let steam = hklm.open_subkey(r#"SOFTWARE\WOW6432Node\Valve\Steam"#)?;
-    steam.get_value("InstallPath")
+fn steam_command(steam_type: &SteamKind, url: &str) -> Option<Command> {
+    let mut command = match steam_type {
+        SteamKind::Vanilla => Command::new(r#"C:\Program Files (x86)\Steam\steam.exe"#),
+        SteamKind::AltPath(path) => Command::new(path.join("steam.exe")),
+        SteamKind::NotFound => return None,
+    };
+    command.arg(url);
+    Some(command)
 }
 
-/// Detect if Steam is installed.
+/// Command opening the url with the appropriate Steam environment, `None`
+/// without Steam.
 #[cfg(target_os = "macos")]
-fn detect_steam() -> SteamKind {
-    let has_steam_vanilla = which::which("steam").is_ok();
-    match has_steam_vanilla {
-        true => SteamKind::Vanilla,
-        _ => SteamKind::NotFound,
+fn steam_command(steam_type: &SteamKind, url: &str) -> Option<Command> {
+    let mut command = match steam_type {
+        // Let macOS hand the url to Steam.app, there might be no `steam` binary.
+        SteamKind::Vanilla | SteamKind::AltPath(_) => Command::new("open"),
+        SteamKind::NotFound => return None,
+    };
+    command.arg(url);
+    Some(command)
+}
+
+/// The commands launching `game`, switching to Big Picture first if asked
+/// to.
+fn launch_commands(
+    opts: &Opts,
+    steam_type: &SteamKind,
+    game: &Game,
+) -> Result<Vec<Command>, String> {
+    let url = opts.game_url(game);
+    // Switching to Big Picture first works whether Steam is already running
+    // or gets started by it.
+    let mut urls = vec![url.as_str()];
+    if opts.big_picture {
+        urls.insert(0, BIG_PICTURE_URL);
     }
+    urls.into_iter()
+        .map(|url| url_command(opts, steam_type, url))
+        .collect()
 }
 
-/// Launche the game from its id using the appropriate Steam environment
-#[cfg(target_os = "linux")]
-fn run(steam_type: SteamKind, id: &str) -> std::io::Result<Child> {
-    let child = match steam_type {
-        SteamKind::Flatpak => std::process::Command::new("flatpak")
-            .args([
-                "run",
-                "com.valvesoftware.Steam",
-                &generate_steam_rungame(id),
-            ])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?,
-        SteamKind::Vanilla => std::process::Command::new("steam")
-            .arg(&generate_steam_rungame(id))
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?,
-        SteamKind::NotFound => panic!("Couldn't find steam!"),
-    };
-    println!("{:?} {} {:?}", steam_type, id, child);
-    Ok(child)
+/// The command opening the steam:// `url`, with the --steam-binary if given.
+fn url_command(opts: &Opts, steam_type: &SteamKind, url: &str) -> Result<Command, String> {
+    match &opts.steam_binary {
+        Some(binary) => Ok(binary_command(binary, url)),
+        None => steam_command(steam_type, url).ok_or_else(|| "couldn't find Steam".to_string()),
+    }
 }
 
-/// Launch the game from its id using the appropriate Steam environment
-#[cfg(target_os = "windows")]
-fn run(steam_type: SteamKind, id: &str) -> std::io::Result<Child> {
-    let binary_path: String = match steam_type {
-        SteamKind::Vanilla => r#"C:\Program Files (x86)\Steam\steam.exe"#.into(),
-        SteamKind::AltPath(binary_path) => binary_path
-            .join("steam.exe")
-            .into_os_string()
-            .into_string()
-            .unwrap(),
-        _ => panic!("Couldn't find steam!"),
-    };
-    Command::new(&binary_path)
-        .arg(&generate_steam_rungame(id))
+/// Start `command` without waiting for it.
+fn spawn_command(opts: &Opts, mut command: Command) -> Result<(), String> {
+    if opts.verbose > 0 {
+        info!(opts, "Running {:?}", command);
+    }
+    command
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
+        .map_err(|err| {
+            format!(
+                "couldn't run {}: {}",
+                Path::new(command.get_program()).display(),
+                err
+            )
+        })?;
+    Ok(())
 }
 
-/// Launche the game from its id using the appropriate Steam environment
-#[cfg(target_os = "macos")]
-fn run(steam_type: SteamKind, id: &str) -> std::io::Result<Child> {
-    let child = match steam_type {
-        SteamKind::Vanilla => std::process::Command::new("steam")
-            .arg(&generate_steam_rungame(id))
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?,
-        SteamKind::NotFound => panic!("Couldn't find steam!"),
-    };
-    Ok(child)
+/// Launch `game` with the commands from `launch_commands`.
+fn launch_game(opts: &Opts, steam_type: &SteamKind, game: &Game) -> Result<(), String> {
+    for command in launch_commands(opts, steam_type, game)? {
+        spawn_command(opts, command)?;
+    }
+    Ok(())
+}
+
+/// The header image Steam cached for the game `appid`, if any.
+fn header_image(steam_root: &Path, appid: &str) -> Option<PathBuf> {
+    let cache = steam_root.join(LIBRARY_CACHE_DIR);
+    [
+        cache.join(appid).join("header.jpg"),
+        cache.join(format!("{}_header.jpg", appid)),
+    ]
+    .into_iter()
+    .find(|image| image.is_file())
+}
+
+/// The installed copy of the game `appid`, if Steam finished installing it.
+fn find_installed(steam_root: &Path, extra_libraries: &[PathBuf], appid: &str) -> Option<Game> {
+    discover_games_in(steam_root, extra_libraries, true)
+        .ok()?
+        .into_iter()
+        .find(|game| game.appid == appid && game.is_fully_installed())
+}
+
+/// Open Steam's install dialog for `game`, or launch it if it's installed
+/// already. With --wait-install, wait for the install to finish and launch
+/// it.
+fn install_game(
+    opts: &Opts,
+    steam_type: &SteamKind,
+    steam_root: &Path,
+    extra_libraries: &[PathBuf],
+    game: &Game,
+) -> Result<(), String> {
+    if let Some(installed) = find_installed(steam_root, extra_libraries, &game.appid) {
+        info!(
+            opts,
+            "\"{}\" is already installed, launching it.", game.name
+        );
+        return launch_game(opts, steam_type, &installed);
+    }
+    let url = generate_steam_install(&game.appid);
+    spawn_command(opts, url_command(opts, steam_type, &url)?)?;
+    if !opts.wait_install {
+        return Ok(());
+    }
+    info!(opts, "Waiting for \"{}\" to be installed...", game.name);
+    loop {
+        std::thread::sleep(INSTALL_POLL_INTERVAL);
+        if let Some(installed) = find_installed(steam_root, extra_libraries, &game.appid) {
+            return launch_game(opts, steam_type, &installed);
+        }
+    }
 }
 
 /// Randomly picks an installed game from your Steam library and launches it.
+///
+/// Without a subcommand, a game is picked and launched.
 #[derive(Parser)]
 #[clap(
-    version = VERSION
+    version = VERSION,
+    args_conflicts_with_subcommands = true
 )]
 struct Opts {
-    /// Show short message telling which game is being launched
+    #[clap(subcommand)]
+    command: Option<Commands>,
+    /// Show short message telling which game is being launched. Repeat for
+    /// more: -vv also tells how many games were found and blacklisted, -vvv
+    /// lists every game found.
     #[clap(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
-    /// Runs the program but doesn't launch the game.
-    #[clap(short, long)]
+    /// Pick a game and print its app id and name, and the command that would
+    /// launch it, without launching it.
+    #[clap(short, long, overrides_with = "no_dry_run")]
     dry_run: bool,
+    /// Launch the game even if the config file sets `dry-run`.
+    #[clap(long, overrides_with = "dry_run")]
+    no_dry_run: bool,
+    /// Print every game that could be picked instead of launching one.
+    #[clap(short, long, conflicts_with = "dry_run")]
+    list: bool,
+    /// Never pick games whose name contains NAME (case-insensitive). Can be repeated.
+    #[clap(short, long, value_name = "NAME")]
+    exclude: Vec<String>,
+    /// Never pick the game with app id APPID. Can be repeated.
+    #[clap(long, value_name = "APPID", visible_alias = "exclude-appid", value_parser = parse_appid)]
+    exclude_id: Vec<String>,
+    /// Only pick the game with app id APPID, which must be installed. Can be
+    /// repeated.
+    #[clap(long, value_name = "APPID", value_parser = parse_appid)]
+    only_appid: Vec<String>,
+    /// Only pick games whose name matches REGEX (case-insensitive). A plain
+    /// word like "souls" matches any name containing it. Can be repeated, see
+    /// --filter-mode.
+    #[clap(short, long, value_name = "REGEX")]
+    filter: Vec<String>,
+    /// Whether games must match any or all of the --filter options. Games
+    /// left out by --exclude or --exclude-id stay out even if they match.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value_t = FilterMode::Any
+    )]
+    filter_mode: FilterMode,
+    /// Seed the random pick so it can be replayed. The same seed gives the
+    /// same game only as long as the installed games don't change.
+    #[clap(long, value_name = "SEED", env = "STEAM_RANDOMISER_SEED")]
+    seed: Option<u64>,
+    /// Pick COUNT different games and print them in the order they were
+    /// drawn. Only the first one is launched.
+    #[clap(
+        short,
+        long,
+        value_name = "COUNT",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    count: u64,
+    /// Pick from the installed games or from every game the --user owns,
+    /// according to the Steam Web API. Games that aren't installed can't be
+    /// launched, so with "owned" the pick and its store page are printed.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "POOL",
+        default_value_t = Pool::Installed
+    )]
+    pool: Pool,
+    /// Steam Web API key used by --pool owned, from
+    /// https://steamcommunity.com/dev/apikey.
+    #[clap(long, value_name = "KEY", env = "STEAM_RANDOMISER_API_KEY")]
+    api_key: Option<String>,
+    /// Reuse the games owned fetched less than DURATION ago, like 90s, 20m or
+    /// 1h30m.
+    #[clap(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        default_value = "24h"
+    )]
+    owned_ttl: Duration,
+    /// Read default options from the config file PATH instead of
+    /// steam_randomiser/config.toml in your config directory.
+    #[clap(long, value_name = "PATH", conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+    /// Don't read the config file.
+    #[clap(long)]
+    no_config: bool,
+    /// Read extra games to never pick from PATH instead of the default
+    /// steam_randomiser/blacklist.txt in your config directory. Each line is
+    /// a game name or `appid:<id>`, lines starting with `#` are ignored.
+    #[clap(long, value_name = "PATH", env = "STEAM_RANDOMISER_BLACKLIST")]
+    blacklist_file: Option<PathBuf>,
+    /// Scan the Steam directory PATH for games instead of the one of the
+    /// detected Steam install. Steam itself is still used to launch the game.
+    #[clap(long, value_name = "PATH", env = "STEAM_RANDOMISER_ROOT")]
+    steam_root: Option<PathBuf>,
+    /// Which Steam install to use when several are installed. "auto" prefers
+    /// a native install over the flatpak, and the flatpak over the snap.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "KIND",
+        env = "STEAM_RANDOMISER_KIND",
+        default_value_t = SteamKindArg::Auto
+    )]
+    steam_kind: SteamKindArg,
+    /// Launch games with the Steam executable PATH, e.g. a wrapper script,
+    /// instead of the detected one.
+    #[clap(long, value_name = "PATH", value_parser = parse_steam_binary)]
+    steam_binary: Option<PathBuf>,
+    /// Only pick among these GAMES, by name or app id. App ids are launched
+    /// even if they aren't installed.
+    #[clap(value_name = "GAMES")]
+    games: Vec<String>,
+    /// Also read GAMES from stdin, one per line.
+    #[clap(long)]
+    stdin: bool,
+    /// Launch the game with app id APPID without looking for games, to check
+    /// that launching works on its own.
+    #[clap(
+        long,
+        value_name = "APPID",
+        value_parser = parse_appid,
+        conflicts_with_all = ["list", "list_users", "list_libraries", "stats", "pick", "confirm", "tui"]
+    )]
+    appid: Option<String>,
+    /// Use the data of the Steam account USER, by persona name, account name,
+    /// SteamID64 or account id, and only pick games it has in its local
+    /// config. Without it, playtime and non-Steam games are those of the
+    /// account that logged in last.
+    #[clap(long, value_name = "USER")]
+    user: Option<String>,
+    /// Print the Steam accounts that logged in on this install, with the
+    /// account id of their userdata folder, instead of launching a game.
+    #[clap(long, conflicts_with_all = ["dry_run", "list"])]
+    list_users: bool,
+    /// Print every Steam library folder that is scanned for games, with how
+    /// many games each has, instead of launching a game.
+    #[clap(long, conflicts_with_all = ["dry_run", "list", "list_users"])]
+    list_libraries: bool,
+    /// Print how many games were found, in which libraries, and how many each
+    /// filter excluded, instead of launching a game.
+    #[clap(long, conflicts_with_all = ["dry_run", "list", "list_users", "list_libraries"])]
+    stats: bool,
+    /// Write every game that could be picked as FORMAT instead of launching
+    /// one: "csv" to stdout or "csv:PATH" to a file.
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        value_parser = parse_export,
+        conflicts_with_all = ["dry_run", "list", "list_users", "list_libraries", "stats"]
+    )]
+    export: Option<Export>,
+    /// Let --export replace a file that already exists.
+    #[clap(long, requires = "export")]
+    force: bool,
+    /// Wait for the launched game to exit and print how long it ran. Ctrl+C
+    /// stops waiting and leaves the game running.
+    #[clap(long, conflicts_with_all = ["dry_run", "list", "id_only", "print_url"])]
+    wait: bool,
+    /// Stop the launched game once it ran for DURATION, like 90s, 20m or
+    /// 1h30m.
+    #[clap(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with_all = ["dry_run", "list", "id_only", "print_url"]
+    )]
+    timeout: Option<Duration>,
+    /// With --timeout, only notify that the time is up instead of stopping
+    /// the game.
+    #[clap(long, requires = "timeout")]
+    timeout_soft: bool,
+    /// Switch Steam to Big Picture mode before launching the game.
+    #[clap(long)]
+    big_picture: bool,
+    /// Launch the game with extra command line ARGS, e.g. "-novid -fullscreen".
+    #[clap(long, value_name = "ARGS", allow_hyphen_values = true)]
+    game_args: Option<String>,
+    /// Only pick games listed in PATH, one game name or app id per line.
+    /// Blacklisted games are still never picked.
+    #[clap(long, value_name = "PATH")]
+    only_from: Option<PathBuf>,
+    /// Also pick games that are still downloading, updating, uninstalling or
+    /// only partially installed.
+    #[clap(
+        long,
+        visible_alias = "include-incomplete",
+        overrides_with = "no_include_uninstalled"
+    )]
+    include_uninstalled: bool,
+    /// Only pick fully installed games, even if the config file sets
+    /// `include-uninstalled`.
+    #[clap(long, overrides_with = "include_uninstalled")]
+    no_include_uninstalled: bool,
+    /// Also pick tools and applications, like benchmarks, dedicated servers
+    /// or Blender. Steam's runtimes like Proton are tools too.
+    #[clap(long, conflicts_with = "music", overrides_with = "no_include_tools")]
+    include_tools: bool,
+    /// Don't pick tools, even if the config file sets `include-tools`.
+    #[clap(long, overrides_with = "include_tools")]
+    no_include_tools: bool,
+    /// Also pick non-Steam games added to the library, of the --user.
+    #[clap(long)]
+    include_shortcuts: bool,
+    /// Don't skip games whose install folder was deleted without Steam
+    /// noticing.
+    #[clap(long)]
+    no_verify: bool,
+    /// Don't pick any of the last N launched games, unless that leaves
+    /// nothing to pick.
+    #[clap(long, value_name = "N")]
+    no_repeat: Option<usize>,
+    /// Scan every library again instead of reusing the games found by the
+    /// previous run for libraries that didn't change.
+    #[clap(long)]
+    refresh: bool,
+    /// Favour some games over others. "least-played" favours games not played
+    /// for the longest time, "playtime" games with the least hours played;
+    /// never played games are favoured most by both. "most-played" favours
+    /// games with the most hours played, "uniform" none.
+    #[clap(long, value_enum, value_name = "MODE")]
+    weighted: Option<Weighting>,
+    /// Same as --weighted playtime: favour games you barely played.
+    #[clap(long, conflicts_with = "weighted")]
+    weight_by_playtime: bool,
+    /// Only pick games you never played, according to the playtime Steam
+    /// keeps locally. Falls back to the least played games if there are none.
+    #[clap(long, overrides_with = "no_never_played")]
+    never_played: bool,
+    /// Also pick played games, even if the config file sets `never-played`.
+    #[clap(long, overrides_with = "never_played")]
+    no_never_played: bool,
+    /// Only pick games using at least SIZE on disk, e.g. 500MB or 2GiB. A
+    /// plain number is in GB.
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+    /// Only pick games using at most SIZE on disk, e.g. 500MB or 20GiB. A
+    /// plain number is in GB.
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+    /// Also exclude games of unknown size when using --min-size or --max-size.
+    #[clap(long)]
+    strict_size: bool,
+    /// Don't pick VR games, according to the Steam store. Games the store
+    /// doesn't know about are assumed not to be VR.
+    #[clap(long, conflicts_with = "vr_only", overrides_with = "no_exclude_vr")]
+    exclude_vr: bool,
+    /// Also pick VR games, even if the config file sets `exclude-vr`.
+    #[clap(long, overrides_with = "exclude_vr")]
+    no_exclude_vr: bool,
+    /// Only pick VR games, according to the Steam store.
+    #[clap(long)]
+    vr_only: bool,
+    /// Jukebox mode: pick a soundtrack instead of a game and open it in Steam's
+    /// music player.
+    #[clap(long)]
+    music: bool,
+    /// Only pick games of the genre NAME, like "RPG" or "Strategy", according
+    /// to the app info Steam keeps locally. Can be repeated, games of any of
+    /// the genres are picked.
+    #[clap(long, value_name = "NAME")]
+    genre: Vec<String>,
+    /// Print the genres of the games that could be picked, with how many
+    /// games have each, instead of launching one.
+    #[clap(long, conflicts_with_all = ["dry_run", "list"])]
+    list_genres: bool,
+    /// Only pick games with the store tag TAG, like "Roguelike". Can be
+    /// repeated, games must have all of them.
+    #[clap(long, value_name = "TAG")]
+    tag: Vec<String>,
+    /// With --tag, pick games having any of the tags instead of all of them.
+    #[clap(long, requires = "tag")]
+    any_tag: bool,
+    /// Only pick games for at least N players, according to the Steam store.
+    /// The store doesn't tell how many players a game takes, so above 1 any
+    /// multiplayer game is picked. Games it can't tell about are skipped.
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    players: Option<u32>,
+    /// With --players, only pick games the players can play together on this
+    /// machine: split screen, shared screen or Remote Play Together.
+    #[clap(long, requires = "players")]
+    local: bool,
+    /// Only pick games with at least LEVEL controller support, according to
+    /// the Steam store. Games it can't tell about count as without support.
+    /// --list shows the support of each game.
+    #[clap(long, value_enum, value_name = "LEVEL")]
+    controller: Option<ControllerSupport>,
+    /// With --controller, also pick games the store can't tell the
+    /// controller support of.
+    #[clap(long, requires = "controller")]
+    controller_unknown_ok: bool,
+    /// Only pick games rated at least LEVEL for the Steam Deck, according to
+    /// the app info Steam keeps locally. Games not rated yet are only picked
+    /// with "any".
+    #[clap(
+        long,
+        value_enum,
+        value_name = "LEVEL",
+        default_value_t = DeckCompat::Any
+    )]
+    deck_compat: DeckCompat,
+    /// Only pick games that run natively on this system rather than through
+    /// Proton, according to the systems listed in the app info Steam keeps
+    /// locally. Games it has no list for are kept.
+    #[clap(long, overrides_with = "no_native_only")]
+    native_only: bool,
+    /// Also pick games that run through Proton, even if the config file sets
+    /// `native-only`.
+    #[clap(long, overrides_with = "native_only")]
+    no_native_only: bool,
+    /// Only pick games with a Metacritic score of at least SCORE, according
+    /// to the app info Steam keeps locally. Games without a score are
+    /// excluded too.
+    #[clap(long, value_name = "SCORE", value_parser = clap::value_parser!(u8).range(0..=100))]
+    min_metacritic: Option<u8>,
+    /// Only pick games in the Steam collection NAME of the --user.
+    #[clap(long, value_name = "NAME")]
+    collection: Option<String>,
+    /// Don't pick games played in the last DAYS days. Games never played are
+    /// always kept.
+    #[clap(long, value_name = "DAYS", visible_alias = "exclude-recent-days")]
+    not_played_within: Option<u64>,
+    /// Ask before launching the picked game, with the option to reroll.
+    #[clap(long, visible_alias = "interactive")]
+    confirm: bool,
+    /// Choose the game yourself from a fuzzy finder over the library, starting
+    /// on the random pick. Escape aborts.
+    #[clap(long, conflicts_with_all = ["confirm", "list"])]
+    pick: bool,
+    /// Show the games full screen and spin for the pick, with keys to reroll,
+    /// blacklist the highlighted game and toggle some filters. Enter launches
+    /// the highlighted game.
+    #[clap(long, conflicts_with_all = ["confirm", "pick", "list"])]
+    tui: bool,
+    /// Cycle through game names like a slot machine before revealing the
+    /// pick. Only in a terminal, the output is the same as without it.
+    #[clap(long, conflicts_with_all = ["list", "tui"])]
+    spin: bool,
+    /// Output format. With json, the pick (or the --list) is printed as JSON
+    /// on stdout and all other messages go to stderr.
+    #[clap(
+        long,
+        visible_alias = "format",
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = Output::Text
+    )]
+    output: Output,
+    /// Same as the completions subcommand.
+    #[clap(long, hide = true, value_enum, value_name = "SHELL")]
+    generate_completions: Option<clap_complete::Shell>,
+    /// Show a desktop notification telling which game was picked, for when
+    /// there's no terminal to read it in.
+    #[clap(long, overrides_with = "no_notify")]
+    notify: bool,
+    /// Don't show a notification, even if the config file sets `notify`.
+    #[clap(long, overrides_with = "notify")]
+    no_notify: bool,
+    /// Open the store page of the picked game in Steam instead of launching
+    /// it, e.g. to read its reviews first.
+    #[clap(
+        long,
+        visible_alias = "open-store-page",
+        conflicts_with_all = ["game_args", "wait", "timeout", "install"]
+    )]
+    store: bool,
+    /// Open Steam's install dialog for the picked game, e.g. with --pool owned.
+    /// Games installed already are launched instead.
+    #[clap(long, conflicts_with_all = ["dry_run", "list", "id_only", "print_url", "wait"])]
+    install: bool,
+    /// With --install, wait for the install to finish and launch the game.
+    #[clap(long, requires = "install")]
+    wait_install: bool,
+    /// Only print the app id of the picked game, without launching it.
+    #[clap(long, conflicts_with_all = ["print_url", "output", "list"])]
+    id_only: bool,
+    /// Only print the steam:// url that launches the picked game, without
+    /// launching it.
+    #[clap(long, conflicts_with_all = ["output", "list"])]
+    print_url: bool,
+}
+
+#[derive(clap::Subcommand)]
+enum Commands {
+    /// Look at or forget the games launched so far.
+    History {
+        #[clap(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Print a completion script for SHELL.
+    Completions {
+        #[clap(value_enum, value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+}
+
+impl Opts {
+    /// Whether stdout is reserved for output meant for other programs.
+    fn machine_readable(&self) -> bool {
+        self.output == Output::Json || self.id_only || self.print_url
+    }
+
+    /// The url opening `game`: running it, or its store page with --store.
+    fn game_url(&self, game: &Game) -> String {
+        match self.store {
+            true => generate_steam_store(&game.appid),
+            false => generate_steam_rungame(&game.appid, self.game_args.as_deref()),
+        }
+    }
+
+    /// Make `text` stand out in messages from `info!`, with color when they
+    /// go to a terminal and NO_COLOR isn't set.
+    fn highlight(&self, text: &str) -> String {
+        let terminal = match self.machine_readable() {
+            true => std::io::stderr().is_terminal(),
+            false => std::io::stdout().is_terminal(),
+        };
+        if terminal && std::env::var_os("NO_COLOR").is_none() {
+            format!("{}{}{}", HIGHLIGHT_COLOR, text, RESET_COLOR)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// How the random pick favours some games over others.
+    fn weighting(&self) -> Option<Weighting> {
+        if self.weight_by_playtime {
+            Some(Weighting::Playtime)
+        } else {
+            self.weighted
+        }
+    }
+
+    /// Fill in the options that weren't given on the command line, or in the
+    /// environment, from the config file.
+    fn merge_config(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), String> {
+        // A flag is also given when its `--no-` negation is.
+        let unset = |id: &str, negated: bool| !negated && !is_given(matches, id);
+        if !is_given(matches, "verbose") {
+            self.verbose = config.verbose.unwrap_or(0);
+        }
+        if unset("dry_run", self.no_dry_run) {
+            // Options that don't go with --dry-run turn it off.
+            self.dry_run = config.dry_run && !(self.list || self.install);
+        }
+        if unset("notify", self.no_notify) {
+            self.notify = config.notify;
+        }
+        if !is_given(matches, "api_key") {
+            self.api_key = config.api_key.clone();
+        }
+        if !is_given(matches, "steam_kind") {
+            self.steam_kind = config.steam_kind.unwrap_or(SteamKindArg::Auto);
+        }
+
+        let filters = &config.filters;
+        if !is_given(matches, "exclude") {
+            self.exclude = filters.exclude.clone();
+        }
+        if !is_given(matches, "exclude_id") {
+            self.exclude_id = filters
+                .exclude_id
+                .iter()
+                .map(|appid| parse_appid(appid))
+                .collect::<Result<_, _>>()?;
+        }
+        if !is_given(matches, "filter") {
+            self.filter = filters.filter.iter().cloned().collect();
+        }
+        if !is_given(matches, "genre") {
+            self.genre = filters.genre.iter().cloned().collect();
+        }
+        if !is_given(matches, "collection") {
+            self.collection = filters.collection.clone();
+        }
+        if unset("include_uninstalled", self.no_include_uninstalled) {
+            self.include_uninstalled = filters.include_uninstalled;
+        }
+        if unset("include_tools", self.no_include_tools) {
+            self.include_tools = filters.include_tools;
+        }
+        if unset("never_played", self.no_never_played) {
+            self.never_played = filters.never_played;
+        }
+        if !is_given(matches, "not_played_within") {
+            self.not_played_within = filters.not_played_within;
+        }
+        if !is_given(matches, "no_repeat") {
+            self.no_repeat = filters.no_repeat;
+        }
+        if unset("exclude_vr", self.no_exclude_vr) {
+            self.exclude_vr = filters.exclude_vr && !self.vr_only;
+        }
+        if unset("native_only", self.no_native_only) {
+            self.native_only = filters.native_only;
+        }
+        if let (Some(deck_compat), false) = (filters.deck_compat, is_given(matches, "deck_compat"))
+        {
+            self.deck_compat = deck_compat;
+        }
+        if !is_given(matches, "min_size") {
+            self.min_size = filters.min_size.as_deref().map(parse_size).transpose()?;
+        }
+        if !is_given(matches, "max_size") {
+            self.max_size = filters.max_size.as_deref().map(parse_size).transpose()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the picked game should be launched.
+    fn launch(&self) -> bool {
+        !(self.dry_run
+            || self.list
+            || self.list_users
+            || self.list_libraries
+            || self.stats
+            || self.export.is_some()
+            || self.list_genres
+            || self.id_only
+            || self.print_url
+            || self.pool == Pool::Owned
+            || self.install)
+    }
+}
+
+/// Whether the option `id` was given on the command line or through its
+/// environment variable.
+fn is_given(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Where the value of the option `id` came from, for verbose output.
+/// `from_config` tells whether the config file sets it.
+fn setting_source(matches: &ArgMatches, id: &str, from_config: bool) -> &'static str {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => "command line",
+        Some(ValueSource::EnvVariable) => "environment",
+        _ if from_config => "config file",
+        _ => "default",
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
+
+/// Games to pick from, see `--pool`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Pool {
+    /// The games installed in the Steam libraries.
+    Installed,
+    /// Every game the --user owns, installed or not.
+    Owned,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum FilterMode {
+    /// Keep games matching at least one filter.
+    Any,
+    /// Keep games matching every filter.
+    All,
+}
+
+/// Least controller support of games to pick, see `--controller`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum ControllerSupport {
+    /// Only games fully playable with a controller.
+    Full,
+    /// Games at least partly playable with a controller.
+    Partial,
+    /// Any game, only showing its support with --list.
+    Any,
+}
+
+impl ControllerSupport {
+    /// Whether a game with the store's `support` fits, "unknown" if the store
+    /// couldn't tell.
+    fn allows(self, support: &str, unknown_ok: bool) -> bool {
+        match self {
+            _ if support == "unknown" && unknown_ok => true,
+            ControllerSupport::Full => support == "full",
+            ControllerSupport::Partial => support == "full" || support == "partial",
+            ControllerSupport::Any => true,
+        }
+    }
+}
+
+/// Lowest Steam Deck rating of games to pick, see `--deck-compat`.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DeckCompat {
+    /// Only games verified for the Deck.
+    Verified,
+    /// Games verified or playable on the Deck.
+    Playable,
+    /// Any game, rated or not.
+    Any,
+}
+
+impl DeckCompat {
+    /// Whether a game with the Deck `category` from the app info fits.
+    fn allows(self, category: Option<u8>) -> bool {
+        match self {
+            DeckCompat::Verified => category == Some(DECK_VERIFIED),
+            DeckCompat::Playable => category >= Some(DECK_PLAYABLE),
+            DeckCompat::Any => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Weighting {
+    /// Favour games by how long ago they were last played.
+    LeastPlayed,
+    /// Favour games by how little time was spent in them.
+    Playtime,
+    /// Favour games by how much time was spent in them.
+    MostPlayed,
+    /// Every game is as likely, like without --weighted.
+    Uniform,
+}
+
+/// Print how many games have each genre, the most common first.
+fn print_genres(genres: &HashMap<String, Vec<String>>) {
+    let mut counts = HashMap::<&str, usize>::new();
+    for names in genres.values() {
+        for name in names {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    for (name, count) in counts {
+        println!("{}\t{}", count, name);
+    }
+}
+
+/// Print the game that would be launched, and how.
+fn print_dry_run(opts: &Opts, steam_type: &SteamKind, game: &Game) {
+    if !opts.machine_readable() {
+        println!("{}\t{}", game.appid, game.name);
+    }
+    match launch_commands(opts, steam_type, game) {
+        Ok(commands) => {
+            for command in commands {
+                match opts.steam_binary {
+                    Some(_) => eprintln!("Would run {:?}", command),
+                    None => eprintln!("Would run {:?} ({:?} Steam)", command, steam_type),
+                }
+            }
+        }
+        Err(err) => eprintln!("Couldn't launch it: {}", err),
+    }
+}
+
+/// Write the completion script of `shell` for every option to `out`.
+fn print_completions(shell: clap_complete::Shell, out: &mut dyn std::io::Write) {
+    let mut command = Opts::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, out);
 }
 
 fn main() {
-    let opts: Opts = Opts::parse();
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Everything `main` does, failing when Steam or its games can't be found.
+fn run() -> Result<(), Error> {
+    let matches = Opts::command().get_matches();
+    let mut opts = Opts::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    match opts.command.take() {
+        Some(Commands::History { command }) => {
+            return run_history_command(command);
+        }
+        Some(Commands::Completions { shell }) => {
+            print_completions(shell, &mut std::io::stdout());
+            return Ok(());
+        }
+        None => {}
+    }
+    if let Some(shell) = opts.generate_completions {
+        print_completions(shell, &mut std::io::stdout());
+        return Ok(());
+    }
+
+    let config_file = match (&opts.config, opts.no_config) {
+        (_, true) => None,
+        (Some(path), _) => Some(path.clone()),
+        // Not having a config is fine, unless one was asked for.
+        (None, _) => config_path().filter(|path| path.is_file()),
+    };
+    let config = match config_file {
+        Some(path) => match load_config(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                return Err(Error::Other(format!(
+                    "couldn't read config {}: {}",
+                    path.display(),
+                    err
+                )))
+            }
+        },
+        None => Config::default(),
+    };
+    opts.merge_config(&config, &matches)
+        .map_err(|err| Error::Other(format!("invalid config: {}", err)))?;
 
-    let steam_type = detect_steam();
+    // Steam itself is only needed to launch games, and to find its directory
+    // when it isn't given.
+    let needs_steam = (opts.launch() || opts.install) && opts.steam_binary.is_none();
+    // A dry run shows what would be launched, so it needs Steam if it's there.
+    let steam_type = match std::env::var_os(STEAM_ROOT_VAR).is_some()
+        || needs_steam
+        || opts.steam_root.is_none()
+        || opts.dry_run
+    {
+        true => detect_steam(opts.steam_kind),
+        false => Err(Error::SteamNotFound(opts.steam_kind.name())),
+    };
+    let steam_type = match steam_type {
+        Ok(steam_type) => steam_type,
+        // A broken STEAM_ROOT is a mistake even when Steam isn't needed.
+        Err(err @ Error::NotSteamDir(_)) => return Err(err),
+        Err(err) if needs_steam => return Err(err),
+        Err(_) => SteamKind::NotFound,
+    };
+    // Without launching anything, any Steam directory will do.
+    let found_root = match (&steam_type, &opts.steam_root) {
+        (SteamKind::NotFound, None) if !needs_steam && opts.steam_kind == SteamKindArg::Auto => {
+            find_steam_dir()
+        }
+        _ => None,
+    };
 
-    if steam_type == SteamKind::NotFound {
-        eprintln!("Couldn't find Steam. Please make sure it is installed.");
-        return;
+    if steam_type == SteamKind::NotFound && opts.steam_root.is_none() && found_root.is_none() {
+        return Err(Error::SteamNotFound(opts.steam_kind.name()));
     }
 
-    let mut path = {
-        let mut home = dirs::home_dir().unwrap();
-        match steam_type {
-            #[cfg(target_os = "linux")]
-            SteamKind::Flatpak => home.push(FLATPAK_APPLICATIONS_PATH),
-            #[cfg(target_os = "linux")]
-            SteamKind::Vanilla => home.push(
-                VANILLA_APPLICATIONS_PATHS
+    if let Some(appid) = &opts.appid {
+        // Launching only needs Steam, its directory only names the game.
+        let root = opts
+            .steam_root
+            .clone()
+            .or(found_root)
+            .or_else(|| steam_dir(&steam_type).ok());
+        let names = root
+            .as_deref()
+            .and_then(|root| AppInfo::read(root, &HashSet::from([appid.as_str()])).ok())
+            .map(|appinfo| appinfo.names())
+            .unwrap_or_default();
+        let game = direct_games(root.as_deref(), &names, &[appid]).remove(0);
+        if opts.dry_run {
+            print_dry_run(&opts, &steam_type, &game);
+        } else if let Err(err) = launch_game(&opts, &steam_type, &game) {
+            return Err(Error::Other(format!(
+                "couldn't launch \"{}\": {}",
+                game.name, err
+            )));
+        }
+        return Ok(());
+    }
+
+    let mut given_games = opts.games.clone();
+    if opts.stdin {
+        let lines = std::io::stdin().lines().collect::<Result<Vec<_>, _>>();
+        match lines {
+            Ok(lines) => given_games.extend(
+                lines
                     .iter()
-                    .find(|&&p| {
-                        let mut test_path = home.to_path_buf();
-                        test_path.push(p);
-                        test_path.exists() && test_path.is_dir()
-                    })
-                    .unwrap(),
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string),
             ),
-            #[cfg(not(target_os = "linux"))]
-            SteamKind::Vanilla => home.push(VANILLA_APPLICATIONS_PATH),
-            SteamKind::AltPath(ref path) => home = path.clone(),
-            _ => {}
+            Err(err) => {
+                return Err(Error::Other(format!(
+                    "couldn't read games from stdin: {}",
+                    err
+                )))
+            }
         }
-        home
-    };
+    }
+    let given_appids = given_games
+        .iter()
+        .filter(|entry| entry.parse::<u64>().is_ok())
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    // Only app ids were given, they can be launched without any library.
+    let only_appids = !given_games.is_empty() && given_appids.len() == given_games.len();
 
-    path.push(MANIFEST_DIR);
+    let steam_root = match (&opts.steam_root, found_root) {
+        (Some(root), _) if only_appids => Ok(root.clone()),
+        (Some(root), _) => check_steam_dir(root).map(|()| root.clone()),
+        (None, Some(root)) => Ok(root),
+        (None, None) => steam_dir(&steam_type),
+    }?;
 
-    let install_dirs = get_other_install_dirs(&path);
+    let user_blacklist = match &opts.blacklist_file {
+        Some(file) => match load_game_list(file) {
+            Ok(entries) => entries,
+            Err(err) => {
+                return Err(Error::Other(format!(
+                    "couldn't read blacklist {}: {}",
+                    file.display(),
+                    err
+                )))
+            }
+        },
+        None => default_blacklist_path()
+            .and_then(|file| load_game_list(&file).ok())
+            .unwrap_or_default(),
+    };
+    let blacklist = Blacklist::new([user_blacklist, config.blacklist].concat());
 
-    let mut games = get_games_from_manifest_in_path(&path);
+    if opts.verbose > 0 {
+        let root_source = if opts.steam_root.is_some() {
+            setting_source(&matches, "steam_root", false)
+        } else if matches!(steam_type, SteamKind::AltPath(_)) {
+            STEAM_ROOT_VAR
+        } else {
+            "detection"
+        };
+        info!(
+            opts,
+            "Steam directory {} (from {})",
+            steam_root.display(),
+            root_source
+        );
+        info!(
+            opts,
+            "Steam install {:?} (--steam-kind from {})",
+            steam_type,
+            setting_source(&matches, "steam_kind", config.steam_kind.is_some())
+        );
+        if let Some(file) = &opts.blacklist_file {
+            info!(
+                opts,
+                "Blacklist file {} (from {})",
+                file.display(),
+                setting_source(&matches, "blacklist_file", false)
+            );
+        }
+        if opts.pool == Pool::Installed {
+            for library in library_dirs(&steam_root, &config.libraries)? {
+                if !library.is_dir() {
+                    info!(
+                        opts,
+                        "Skipping library {}, its drive isn't mounted",
+                        library.display()
+                    );
+                }
+            }
+        }
+    }
 
-    for other_dir in install_dirs {
-        let mut path = PathBuf::new();
-        path.push(other_dir);
-        path.push(MANIFEST_DIR);
-        games.extend(get_games_from_manifest_in_path(&path));
+    if opts.list_libraries {
+        for library in library_dirs(&steam_root, &config.libraries)? {
+            let source = if library == steam_root {
+                "Steam directory"
+            } else if config.libraries.contains(&library) {
+                "config file"
+            } else {
+                "libraryfolders.vdf"
+            };
+            let games = match count_manifests(&library) {
+                _ if !library.is_dir() => "missing".to_string(),
+                Ok(count) => format!("{} games", count),
+                Err(err) => {
+                    eprintln!(
+                        "Warning: couldn't read {}: {}",
+                        library.join(MANIFEST_DIR).display(),
+                        err
+                    );
+                    "unreadable, skipped".to_string()
+                }
+            };
+            println!("{}\t{}\t(from {})", library.display(), games, source);
+        }
+        return Ok(());
     }
 
-    let (game, id) = games.choose(&mut rand::thread_rng()).unwrap();
+    if opts.list_users {
+        match read_login_users(&steam_root) {
+            Ok(users) => {
+                for user in users {
+                    let most_recent = if user.most_recent {
+                        " (most recent)"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "{}\t{} ({}){}",
+                        user.account_id(),
+                        user.persona_name,
+                        user.account_name,
+                        most_recent
+                    );
+                }
+            }
+            Err(err) => {
+                return Err(Error::Other(format!(
+                    "couldn't read the Steam accounts: {}",
+                    err
+                )))
+            }
+        }
+        return Ok(());
+    }
 
+    let user = select_user(&steam_root, opts.user.as_deref())
+        .map_err(|err| Error::Other(format!("invalid --user: {}", err)))?;
     if opts.verbose > 0 {
-        println!("Randomly launching \"{}\"! Have fun!", game);
+        if let Some(steam_id) = user {
+            let source = if opts.user.is_some() {
+                "command line"
+            } else {
+                "most recent login"
+            };
+            info!(opts, "Using Steam account {} (from {})", steam_id, source);
+        }
+    }
+
+    let mut games = match opts.pool {
+        Pool::Installed if only_appids => Vec::new(),
+        Pool::Installed => discover_games_in(&steam_root, &config.libraries, opts.refresh)?,
+        Pool::Owned => load_owned_games(&opts, user)?,
+    };
+    // Given app ids are picked from even if they aren't installed.
+    let missing_appids = given_appids
+        .into_iter()
+        .filter(|appid| !games.iter().any(|game| game.appid == *appid))
+        .collect::<Vec<_>>();
+    // Parsed once for every app, the filters below only look up fewer of them.
+    let appinfo = AppInfo::read(
+        &steam_root,
+        &games
+            .iter()
+            .map(|game| game.appid.as_str())
+            .chain(missing_appids.iter().copied())
+            .collect(),
+    );
+    let names = appinfo.as_ref().map(AppInfo::names).unwrap_or_default();
+    if !missing_appids.is_empty() {
+        games.extend(direct_games(Some(&steam_root), &names, &missing_appids));
+    }
+    for appid in &opts.only_appid {
+        if !games.iter().any(|game| &game.appid == appid) {
+            return Err(Error::NoGames(format!(
+                "--only-appid {} isn't installed, pick it from every game you own with --pool owned or launch it directly with --appid {}",
+                appid, appid
+            )));
+        }
+    }
+    if opts.verbose > 1 {
+        let libraries = games
+            .iter()
+            .map(|game| &game.library)
+            .collect::<HashSet<_>>();
+        info!(
+            opts,
+            "Found {} games in {} libraries",
+            games.len(),
+            libraries.len()
+        );
+    }
+    if opts.verbose > 2 {
+        for game in &games {
+            info!(
+                opts,
+                "  {}\t{}\t{}",
+                game.appid,
+                game.name,
+                game.library.display()
+            );
+        }
+    }
+    fill_missing_names(&opts, &names, &mut games);
+    let filtered = GameFilter {
+        opts: &opts,
+        steam_root: &steam_root,
+        user,
+        appinfo: &appinfo,
+        blacklist: &blacklist,
+        given_games: &given_games,
+    }
+    .apply(games)?;
+    let Filtered {
+        games,
+        stats,
+        controller_support,
+        genres,
+        no_match,
+    } = filtered;
+
+    if let Some(genres) = genres {
+        print_genres(&genres);
+        return Ok(());
+    }
+
+    if opts.stats {
+        match opts.output {
+            Output::Json => println!("{}", serde_json::to_string(&stats).unwrap()),
+            Output::Text => stats.print(),
+        }
+        return Ok(());
+    }
+
+    if let Some(export) = &opts.export {
+        if let Err(err) = export_games(export, &games, opts.force) {
+            let path = export.path.as_deref().unwrap_or(Path::new("stdout"));
+            return Err(Error::Other(match err.kind() {
+                std::io::ErrorKind::AlreadyExists => format!(
+                    "{} already exists, use --force to replace it",
+                    path.display()
+                ),
+                _ => format!("couldn't export to {}: {}", path.display(), err),
+            }));
+        }
+        return Ok(());
     }
 
-    if !opts.dry_run {
-        let _ = run(steam_type, id).unwrap();
+    if opts.list {
+        if opts.output == Output::Json {
+            println!("{}", serde_json::to_string(&games).unwrap());
+        } else {
+            for game in &games {
+                let mut line = format!("{}\t{}", game.appid, game.name);
+                if opts.min_size.is_some() || opts.max_size.is_some() {
+                    let size = match game.size_on_disk {
+                        0 => "unknown size".to_string(),
+                        size => format_size(size),
+                    };
+                    line = format!("{}\t{}", line, size);
+                }
+                if let Some(support) = controller_support.get(&game.appid) {
+                    line = format!("{}\t{} controller support", line, support);
+                }
+                println!("{}", line);
+            }
+        }
+        eprintln!("{} games found", games.len());
+        return Ok(());
+    }
+
+    pick_and_launch(
+        &opts,
+        &matches,
+        &steam_type,
+        &steam_root,
+        &config.libraries,
+        &games,
+        no_match,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(appid: &str, name: &str) -> Game {
+        Game {
+            name: name.to_string(),
+            appid: appid.to_string(),
+            library: PathBuf::new(),
+            last_played: 0,
+            state_flags: STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime: None,
+            install_dir: String::new(),
+        }
+    }
+
+    #[test]
+    fn names_nameless_games_from_app_info_or_drops_them() {
+        let opts = Opts::parse_from(["steam_randomiser"]);
+        let names = HashMap::from([("620".to_string(), "Portal 2".to_string())]);
+        let mut games = vec![
+            game("10", "Counter-Strike"),
+            game("620", ""),
+            game("999", ""),
+        ];
+        fill_missing_names(&opts, &names, &mut games);
+        let games = games
+            .iter()
+            .map(|game| game.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(games, ["Counter-Strike", "Portal 2"]);
+    }
+
+    #[test]
+    fn completions_have_the_long_options() {
+        use clap_complete::Shell;
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let mut script = Vec::new();
+            print_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            for option in ["dry-run", "exclude-id", "filter", "weighted", "steam-kind"] {
+                // Fish lists long options without their dashes.
+                let option = match shell {
+                    Shell::Fish => format!("-l {}", option),
+                    _ => format!("--{}", option),
+                };
+                assert!(
+                    script.contains(&option),
+                    "{} completions lack {}",
+                    shell,
+                    option
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,119 @@
+use crate::unix_now;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const OWNED_CACHE_FILE: &str = "steam_randomiser/owned.json";
+
+const OWNED_GAMES_URL: &str = "https://api.steampowered.com/IPlayerService/GetOwnedGames/v1/";
+
+/// A game owned by the account, installed or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedGame {
+    pub appid: String,
+    pub name: String,
+    /// Minutes played on any machine.
+    pub playtime: u64,
+    /// Unix time the game was last played, 0 if it never was.
+    pub last_played: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OwnedCache {
+    steam_id: u64,
+    /// Unix time the games were fetched.
+    fetched: u64,
+    games: Vec<OwnedGame>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(OWNED_CACHE_FILE))
+}
+
+/// Read the cached games of `steam_id` if they're younger than `ttl`.
+fn load_cache(path: &Path, steam_id: u64, ttl: Duration) -> Option<Vec<OwnedGame>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let cache = serde_json::from_str::<OwnedCache>(&contents).ok()?;
+    let age = unix_now().saturating_sub(cache.fetched);
+    match cache.steam_id == steam_id && age < ttl.as_secs() {
+        true => Some(cache.games),
+        false => None,
+    }
+}
+
+fn save_cache(path: &Path, cache: &OwnedCache) -> std::io::Result<()> {
+    crate::write_atomic(path, serde_json::to_string(cache)?.as_bytes())
+}
+
+/// Ask the Steam Web API for the games of the account `steam_id`.
+fn fetch_owned_games(api_key: &str, steam_id: u64) -> Result<Vec<OwnedGame>, String> {
+    let response = ureq::get(OWNED_GAMES_URL)
+        .timeout(Duration::from_secs(30))
+        .query("key", api_key)
+        .query("steamid", &steam_id.to_string())
+        .query("include_appinfo", "1")
+        .query("include_played_free_games", "1")
+        .call();
+    let response: Value = match response {
+        Ok(response) => response.into_json().map_err(|err| err.to_string())?,
+        Err(ureq::Error::Status(401 | 403, _)) => {
+            return Err("the Steam Web API key was refused".to_string())
+        }
+        Err(ureq::Error::Status(429, _)) => {
+            return Err("too many requests to the Steam Web API, try again later".to_string())
+        }
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(format!("the Steam Web API answered with error {}", code))
+        }
+        // The error's url has the API key in it, leave it out.
+        Err(ureq::Error::Transport(err)) => {
+            return Err(format!("couldn't reach the Steam Web API: {}", err.kind()))
+        }
+    };
+    // Private profiles get an empty response rather than an error.
+    let games = response["response"]["games"].as_array().ok_or_else(|| {
+        format!(
+            "the games of account {} are private, make them public in its Steam privacy settings",
+            steam_id
+        )
+    })?;
+    Ok(games
+        .iter()
+        .filter_map(|game| {
+            Some(OwnedGame {
+                appid: game["appid"].as_u64()?.to_string(),
+                name: game["name"].as_str()?.to_string(),
+                playtime: game["playtime_forever"].as_u64().unwrap_or(0),
+                last_played: game["rtime_last_played"].as_u64().unwrap_or(0),
+            })
+        })
+        .collect())
+}
+
+/// The games owned by the account `steam_id`, a SteamID64, reusing the ones
+/// fetched less than `ttl` ago.
+pub fn owned_games(api_key: &str, steam_id: u64, ttl: Duration) -> Result<Vec<OwnedGame>, String> {
+    let path = cache_path();
+    if let Some(games) = path
+        .as_deref()
+        .and_then(|path| load_cache(path, steam_id, ttl))
+    {
+        return Ok(games);
+    }
+    let games = fetch_owned_games(api_key, steam_id)?;
+    if let Some(path) = path {
+        let cache = OwnedCache {
+            steam_id,
+            fetched: unix_now(),
+            games,
+        };
+        if let Err(err) = save_cache(&path, &cache) {
+            eprintln!("Couldn't save owned games {}: {}", path.display(), err);
+        }
+        return Ok(cache.games);
+    }
+    Ok(games)
+}
@@ -0,0 +1,586 @@
+use crate::{
+    default_blacklist_path,
+    duration::format_duration,
+    header_image,
+    history::{append_history, history_path, HistoryEntry},
+    install_game, launch_game, print_dry_run,
+    running::{notify, stop_game, wait_for_exit, Session, STARTUP_GRACE},
+    setting_source,
+    tui::run_tui,
+    Opts, Output, Pool, Weighting, LAUNCH_RETRIES, SPIN_FIRST_DELAY, SPIN_SLOWDOWN, SPIN_STEPS,
+    STORE_PAGE_URL,
+};
+use clap::ArgMatches;
+use dialoguer::FuzzySelect;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Serialize;
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+use steam_randomiser::{error::Error, unix_now, Game, SteamKind};
+
+/// A picked game as printed by `--output json`.
+#[derive(Serialize)]
+struct Pick<'a> {
+    #[serde(flatten)]
+    game: &'a Game,
+    launched: bool,
+}
+
+impl Weighting {
+    /// How likely `game` is to be picked relative to others.
+    fn weight(self, game: &Game, now: u64) -> f64 {
+        match self {
+            Weighting::LeastPlayed => least_played_weight(game.last_played, now),
+            Weighting::Playtime => playtime_weight(game.playtime),
+            Weighting::MostPlayed => game.playtime.unwrap_or(0) as f64,
+            Weighting::Uniform => 1.0,
+        }
+    }
+
+    /// Whether the weights come from the playtime of games.
+    pub fn uses_playtime(self) -> bool {
+        matches!(self, Weighting::Playtime | Weighting::MostPlayed)
+    }
+}
+
+/// Randomly choose up to `count` different games, favouring some according
+/// to `weighting`.
+pub fn choose_games<'a>(
+    games: &[&'a Game],
+    count: usize,
+    weighting: Option<Weighting>,
+    rng: &mut StdRng,
+) -> Vec<&'a Game> {
+    let now = unix_now();
+    // Weights can all be zero, like most-played when nothing was played, pick
+    // uniformly then.
+    let all_zero =
+        |weighting: Weighting| games.iter().all(|game| weighting.weight(game, now) == 0.0);
+    match weighting.filter(|weighting| !all_zero(*weighting)) {
+        Some(weighting) => games
+            .choose_multiple_weighted(rng, count, |game| weighting.weight(game, now))
+            .map(|picks| picks.copied().collect())
+            .unwrap_or_default(),
+        None => games.choose_multiple(rng, count).copied().collect(),
+    }
+}
+
+/// Ask whether to launch the first of `picks`, rerolling among the games not
+/// shown yet. Returns the game to launch, or `None` if the user said no.
+///
+/// If nobody can answer (stdin isn't a terminal or is closed) the first pick
+/// is launched as if we hadn't asked.
+fn confirm_pick<'a>(
+    games: &[&'a Game],
+    picks: &[&'a Game],
+    weighting: Option<Weighting>,
+    rng: &mut StdRng,
+) -> Option<&'a Game> {
+    let mut pick = picks[0];
+    if !std::io::stdin().is_terminal() {
+        return Some(pick);
+    }
+    let mut shown = picks.iter().map(|game| &game.appid).collect::<Vec<_>>();
+    loop {
+        eprint!("Launch \"{}\"? [Y/n/r] ", pick.name);
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => return Some(pick),
+            Ok(_) => {}
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "" | "y" | "yes" => return Some(pick),
+            "n" | "no" => return None,
+            "r" | "reroll" => {
+                let remaining = games
+                    .iter()
+                    .copied()
+                    .filter(|game| !shown.contains(&&game.appid))
+                    .collect::<Vec<_>>();
+                match choose_games(&remaining, 1, weighting, rng).first() {
+                    Some(game) => pick = game,
+                    None => {
+                        eprintln!("You vetoed everything! Nothing left to launch.");
+                        return None;
+                    }
+                }
+                shown.push(&pick.appid);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What the fuzzy finder lists for `games`, which typing filters, and where
+/// `default` is in it.
+fn picker_items<'a>(games: &[&'a Game], default: &Game) -> (Vec<&'a str>, usize) {
+    let names = games.iter().map(|game| game.name.as_str()).collect();
+    let default_index = games
+        .iter()
+        .position(|game| game.appid == default.appid)
+        .unwrap_or(0);
+    (names, default_index)
+}
+
+/// Let the user choose a game with a fuzzy finder, starting on `default`.
+/// Returns `None` if they aborted.
+///
+/// Without a terminal to interact with, `default` is picked.
+fn pick_interactively<'a>(games: &[&'a Game], default: &'a Game) -> Option<&'a Game> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Some(default);
+    }
+    let (names, default_index) = picker_items(games, default);
+    let selection = FuzzySelect::new()
+        .with_prompt("Pick a game")
+        .items(&names)
+        .default(default_index)
+        .interact_opt();
+    match selection {
+        Ok(selection) => selection.map(|i| games[i]),
+        Err(err) => {
+            eprintln!("Couldn't show the game picker: {}", err);
+            None
+        }
+    }
+}
+
+/// Show random names of `games` in place on stdout, slowing down until
+/// landing on `pick`, and clear the line so the output that follows is the
+/// same as without it.
+fn spin_to_pick(opts: &Opts, games: &[&Game], pick: &Game) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    // Not the seeded generator, so seeds keep picking the same games.
+    let mut rng = rand::thread_rng();
+    for step in 0..SPIN_STEPS {
+        let name = match games.choose(&mut rng) {
+            Some(game) => &game.name,
+            None => break,
+        };
+        write!(stdout, "\r\x1b[2K{}", name)?;
+        stdout.flush()?;
+        std::thread::sleep(SPIN_FIRST_DELAY + SPIN_SLOWDOWN * step * step / SPIN_STEPS);
+    }
+    write!(stdout, "\r\x1b[2K{}", opts.highlight(&pick.name))?;
+    stdout.flush()?;
+    std::thread::sleep(SPIN_FIRST_DELAY * SPIN_STEPS);
+    write!(stdout, "\r\x1b[2K")?;
+    stdout.flush()
+}
+
+/// Weight a game by how long ago it was played. Never played games get the
+/// largest possible weight since `now` is bigger than any elapsed time.
+fn least_played_weight(last_played: u64, now: u64) -> f64 {
+    if last_played == 0 {
+        now as f64
+    } else {
+        now.saturating_sub(last_played).max(1) as f64
+    }
+}
+
+/// Weight a game by 1 / (1 + hours played). Games without playtime data are
+/// treated as never played and get the maximum weight of 1.
+fn playtime_weight(minutes: Option<u64>) -> f64 {
+    1.0 / (1.0 + minutes.unwrap_or(0) as f64 / 60.0)
+}
+
+/// Pick --count games among `games` and launch the first, then do what the
+/// options ask with it. `no_match` tells why there's no game, if known.
+pub fn pick_and_launch(
+    opts: &Opts,
+    matches: &ArgMatches,
+    steam_type: &SteamKind,
+    steam_root: &Path,
+    libraries: &[PathBuf],
+    games: &[Game],
+    no_match: Option<String>,
+) -> Result<(), Error> {
+    let mut rng = seeded_rng(opts, matches);
+    let candidates = games.iter().collect::<Vec<_>>();
+    let mut picks = match choose_picks(opts, &candidates, no_match, &mut rng)? {
+        Some(picks) => picks,
+        None => return Ok(()),
+    };
+    let mut game = picks[0];
+
+    if opts.spin && !opts.machine_readable() && std::io::stdout().is_terminal() {
+        if let Err(err) = spin_to_pick(opts, &candidates, game) {
+            eprintln!("Warning: couldn't show the spin: {}", err);
+        }
+    }
+
+    if opts.verbose > 0 {
+        info!(
+            opts,
+            "Randomly launching \"{}\"! Have fun!",
+            opts.highlight(&game.name)
+        );
+    }
+    if opts.verbose > 1 {
+        info!(opts, "Installed in {}", game.library.display());
+    }
+
+    if opts.launch() {
+        let mut failed = Vec::new();
+        while let Err(err) = launch_game(opts, steam_type, game) {
+            eprintln!("Couldn't launch \"{}\": {}", game.name, err);
+            failed.push(&game.appid);
+            let remaining = candidates
+                .iter()
+                .filter(|game| !failed.contains(&&game.appid))
+                .copied()
+                .collect::<Vec<_>>();
+            let retry = match failed.len() > LAUNCH_RETRIES {
+                true => None,
+                false => choose_games(&remaining, 1, opts.weighting(), &mut rng)
+                    .first()
+                    .copied(),
+            };
+            match retry {
+                Some(next) => {
+                    game = next;
+                    info!(opts, "Trying \"{}\" instead.", opts.highlight(&game.name));
+                }
+                None => {
+                    return Err(Error::Other(format!(
+                        "giving up after {} failed launches",
+                        failed.len()
+                    )))
+                }
+            }
+        }
+        picks[0] = game;
+
+        if let (Some(file), false) = (history_path(), opts.store) {
+            let entry = HistoryEntry {
+                appid: game.appid.clone(),
+                name: game.name.clone(),
+                timestamp: unix_now(),
+            };
+            if let Err(err) = append_history(&file, entry) {
+                eprintln!("Couldn't save history to {}: {}", file.display(), err);
+            }
+        }
+    }
+
+    if opts.notify {
+        let game = picks[0];
+        let message = match opts.dry_run {
+            true => format!("Picked \"{}\" (dry run, not launched)", game.name),
+            false => format!("Launching \"{}\"! Have fun!", game.name),
+        };
+        let image = header_image(steam_root, &game.appid);
+        if let Err(err) = notify("Steam Randomiser", &message, image.as_deref()) {
+            eprintln!("Warning: couldn't show a notification: {}", err);
+        }
+    }
+
+    if opts.dry_run {
+        print_dry_run(opts, steam_type, picks[0]);
+    }
+
+    if opts.pool == Pool::Owned && !opts.dry_run && !opts.machine_readable() {
+        println!(
+            "{}\t{}{}",
+            opts.highlight(&picks[0].name),
+            STORE_PAGE_URL,
+            picks[0].appid
+        );
+    }
+
+    if opts.install {
+        let game = picks[0];
+        if let Err(err) = install_game(opts, steam_type, steam_root, libraries, game) {
+            return Err(Error::Other(format!(
+                "couldn't install \"{}\": {}",
+                game.name, err
+            )));
+        }
+    }
+
+    if opts.id_only || opts.print_url {
+        for game in &picks {
+            if opts.id_only {
+                println!("{}", game.appid);
+            } else {
+                println!("{}", opts.game_url(game));
+            }
+        }
+    }
+
+    if opts.output == Output::Json {
+        let picks = picks
+            .iter()
+            .enumerate()
+            .map(|(i, game)| Pick {
+                game,
+                launched: i == 0 && opts.launch(),
+            })
+            .collect::<Vec<_>>();
+        let json = if opts.count > 1 {
+            serde_json::to_string(&picks)
+        } else {
+            serde_json::to_string(&picks[0])
+        };
+        println!("{}", json.unwrap());
+    }
+
+    if opts.wait || opts.timeout.is_some() {
+        wait_for_game(opts, steam_type, picks[0])?;
+    }
+    Ok(())
+}
+
+/// The generator picks come from, seeded with --seed if given.
+fn seeded_rng(opts: &Opts, matches: &ArgMatches) -> StdRng {
+    let seed = opts.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    if opts.verbose > 0 {
+        match opts.seed {
+            Some(_) => info!(
+                opts,
+                "Using seed {} (from {})",
+                seed,
+                setting_source(matches, "seed", false)
+            ),
+            None => info!(opts, "Using seed {}", seed),
+        }
+    }
+
+    StdRng::seed_from_u64(seed)
+}
+
+/// Randomly pick --count games among `candidates`, letting the user change
+/// the first one with --pick, --tui or --confirm. `None` if they quit.
+fn choose_picks<'a>(
+    opts: &Opts,
+    candidates: &[&'a Game],
+    no_match: Option<String>,
+    rng: &mut StdRng,
+) -> Result<Option<Vec<&'a Game>>, Error> {
+    let count = opts.count as usize;
+    if let Some(weighting) = opts
+        .weighting()
+        .filter(|weighting| weighting.uses_playtime())
+    {
+        if opts.verbose > 1 {
+            let now = unix_now();
+            for game in candidates {
+                info!(opts, "{:.3}\t{}", weighting.weight(game, now), game.name);
+            }
+        }
+    }
+
+    let mut picks = choose_games(candidates, count, opts.weighting(), rng);
+
+    if picks.is_empty() {
+        return Err(Error::NoGames(no_match.unwrap_or_else(|| {
+            "no eligible games found to launch".to_string()
+        })));
+    }
+
+    if count > 1 {
+        if count > picks.len() {
+            eprintln!("Only {} games available, picking all of them.", picks.len());
+        }
+        for (i, game) in picks.iter().enumerate() {
+            info!(opts, "{}. {}\t{}", i + 1, game.name, game.appid);
+        }
+    }
+
+    if opts.pick {
+        match pick_interactively(candidates, picks[0]) {
+            Some(game) => picks[0] = game,
+            None => return Ok(None),
+        }
+    }
+    if opts.tui {
+        if !std::io::stdout().is_terminal() {
+            eprintln!("Warning: --tui needs a terminal, picking at random instead.");
+        } else {
+            let blacklist_file = opts.blacklist_file.clone().or_else(default_blacklist_path);
+            match run_tui(candidates, opts.weighting(), rng, blacklist_file) {
+                Ok(Some(game)) => picks[0] = game,
+                Ok(None) => return Ok(None),
+                Err(err) => {
+                    return Err(Error::Other(format!(
+                        "couldn't show the game picker: {}",
+                        err
+                    )))
+                }
+            }
+        }
+    }
+    if opts.confirm {
+        match confirm_pick(candidates, &picks, opts.weighting(), rng) {
+            Some(game) => picks[0] = game,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(picks))
+}
+
+/// Wait for `game` to exit, stopping it once --timeout runs out. Fails if it
+/// couldn't be stopped or never started.
+fn wait_for_game(opts: &Opts, steam_type: &SteamKind, game: &Game) -> Result<(), Error> {
+    match wait_for_exit(steam_type, &game.appid, opts.timeout) {
+        Session::Ended(played) => {
+            info!(
+                opts,
+                "\"{}\" ran for {}",
+                game.name,
+                format_duration(played)
+            );
+        }
+        Session::Detached(played) => {
+            info!(
+                opts,
+                "Stopped waiting for \"{}\" after {}",
+                game.name,
+                format_duration(played)
+            );
+        }
+        Session::TimedOut if opts.timeout_soft => {
+            let message = format!("Time to stop playing \"{}\"!", game.name);
+            info!(opts, "{}", message);
+            if let Err(err) = notify("Time's up", &message, None) {
+                eprintln!("Warning: couldn't show a notification: {}", err);
+            }
+        }
+        Session::TimedOut => match game.install_path().map(|path| stop_game(&path)) {
+            Some(Ok(stopped)) if stopped > 0 => {
+                info!(opts, "Time's up, stopped \"{}\"", game.name);
+            }
+            Some(Ok(_)) => {
+                eprintln!("Time's up, but no process of \"{}\" was found.", game.name)
+            }
+            Some(Err(err)) => {
+                return Err(Error::Other(format!(
+                    "time's up, but couldn't stop \"{}\": {}",
+                    game.name, err
+                )))
+            }
+            None => {
+                return Err(Error::Other(format!(
+                    "time's up, but the install folder of \"{}\" is unknown so it can't be stopped",
+                    game.name
+                )))
+            }
+        },
+        Session::NeverStarted => {
+            return Err(Error::Other(format!(
+                "\"{}\" didn't start within {}, stopped waiting for it",
+                game.name,
+                format_duration(STARTUP_GRACE)
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(appid: &str, playtime: Option<u64>, last_played: u64) -> Game {
+        Game {
+            name: format!("Game {}", appid),
+            appid: appid.to_string(),
+            library: PathBuf::new(),
+            last_played,
+            state_flags: steam_randomiser::STATE_FULLY_INSTALLED,
+            size_on_disk: 0,
+            playtime,
+            install_dir: String::new(),
+        }
+    }
+
+    /// How many of 1000 single picks out of `games` are the first game.
+    fn first_picked(games: &[Game], weighting: Weighting) -> usize {
+        let games = games.iter().collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..1000)
+            .filter(|_| choose_games(&games, 1, Some(weighting), &mut rng)[0].appid == "1")
+            .count()
+    }
+
+    /// The items the fuzzy finder still shows once `query` is typed, best
+    /// match first, matched the way dialoguer does.
+    fn shown<'a>(items: &[&'a str], query: &str) -> Vec<&'a str> {
+        use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+        let matcher = SkimMatcherV2::default();
+        let mut matches = items
+            .iter()
+            .filter_map(|item| Some((*item, matcher.fuzzy_match(item, query)?)))
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(item, _)| item).collect()
+    }
+
+    #[test]
+    fn picker_starts_on_the_random_pick() {
+        let mut games = [
+            game("10", None, 0),
+            game("20", None, 0),
+            game("30", None, 0),
+        ];
+        games[1].name = "Portal 2".to_string();
+        let games = games.iter().collect::<Vec<_>>();
+        let (items, default_index) = picker_items(&games, games[1]);
+        assert_eq!(items, ["Game 10", "Portal 2", "Game 30"]);
+        assert_eq!(default_index, 1);
+    }
+
+    #[test]
+    fn typing_filters_the_picker_by_name() {
+        let names = [
+            "Half-Life 2",
+            "Portal",
+            "Portal 2",
+            "Hades",
+            "Stardew Valley",
+        ];
+        let mut games = names.map(|_| game("1", None, 0));
+        for (game, name) in games.iter_mut().zip(names) {
+            game.name = name.to_string();
+        }
+        let games = games.iter().collect::<Vec<_>>();
+        let (items, _) = picker_items(&games, games[0]);
+        let mut portals = shown(&items, "portal");
+        portals.sort();
+        assert_eq!(portals, ["Portal", "Portal 2"]);
+        assert_eq!(shown(&items, "hl2"), ["Half-Life 2"]);
+        assert_eq!(shown(&items, "stardew")[0], "Stardew Valley");
+        assert!(shown(&items, "zelda").is_empty());
+    }
+
+    #[test]
+    fn most_played_favours_long_playtimes() {
+        let games = [game("1", Some(6000), 0), game("2", Some(60), 0)];
+        assert!(first_picked(&games, Weighting::MostPlayed) > 900);
+    }
+
+    #[test]
+    fn playtime_favours_short_playtimes() {
+        let games = [game("1", Some(60), 0), game("2", Some(6000), 0)];
+        assert!(first_picked(&games, Weighting::Playtime) > 900);
+    }
+
+    #[test]
+    fn least_played_favours_games_not_played_for_long() {
+        let now = unix_now();
+        let games = [
+            game("1", None, now - 365 * 86400),
+            game("2", None, now - 86400),
+        ];
+        assert!(first_picked(&games, Weighting::LeastPlayed) > 900);
+    }
+
+    #[test]
+    fn most_played_is_uniform_when_nothing_was_played() {
+        let games = [game("1", Some(0), 0), game("2", None, 0)];
+        let picked = first_picked(&games, Weighting::MostPlayed);
+        assert!((400..600).contains(&picked), "{}", picked);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use steam_randomiser::SteamKind;
+
+/// How often Steam is asked which game is running.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a game gets to show up as running before we stop waiting for it,
+/// slow games can take a while to get going after Steam is started.
+pub const STARTUP_GRACE: Duration = Duration::from_secs(120);
+
+/// Location of Steam's `registry.vdf`, where it keeps the running app.
+#[cfg(target_os = "linux")]
+fn registry_path(steam_type: &SteamKind) -> Option<std::path::PathBuf> {
+    let home = dirs::home_dir()?;
+    Some(match steam_type {
+        SteamKind::Flatpak => home.join(".var/app/com.valvesoftware.Steam/.steam/registry.vdf"),
+        SteamKind::Snap => home.join("snap/steam/common/.steam/registry.vdf"),
+        _ => home.join(".steam/registry.vdf"),
+    })
+}
+
+/// Location of Steam's `registry.vdf`, where it keeps the running app.
+#[cfg(target_os = "macos")]
+fn registry_path(_steam_type: &SteamKind) -> Option<std::path::PathBuf> {
+    dirs::home_dir().map(|home| home.join("Library/Application Support/Steam/registry.vdf"))
+}
+
+/// The appid of the game Steam is running, `0` if none.
+#[cfg(not(target_os = "windows"))]
+fn running_appid(steam_type: &SteamKind) -> Option<String> {
+    let registry = steam_randomiser::vdf::parse_vdf(&registry_path(steam_type)?).ok()?;
+    registry
+        .lookup(&[
+            "Registry",
+            "HKCU",
+            "Software",
+            "Valve",
+            "Steam",
+            "RunningAppID",
+        ])?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// The appid of the game Steam is running, `0` if none.
+#[cfg(target_os = "windows")]
+fn running_appid(_steam_type: &SteamKind) -> Option<String> {
+    use winreg::enums::*;
+    let hkcu = winreg::RegKey::predef(HKEY_CURRENT_USER);
+    let steam = hkcu.open_subkey(r#"Software\Valve\Steam"#).ok()?;
+    steam
+        .get_value::<u32, _>("RunningAppID")
+        .ok()
+        .map(|appid| appid.to_string())
+}
+
+/// How waiting for a game ended.
+pub enum Session {
+    /// The game ran for this long and exited.
+    Ended(Duration),
+    /// The game never showed up as running.
+    NeverStarted,
+    /// Ctrl+C was pressed, the game might still be running.
+    Detached(Duration),
+    /// The game was still running when the time limit was reached.
+    TimedOut,
+}
+
+/// Block until the game `appid` has started and exited again, or until it ran
+/// for `timeout`.
+pub fn wait_for_exit(steam_type: &SteamKind, appid: &str, timeout: Option<Duration>) -> Session {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_flag = interrupted.clone();
+    // Without a handler Ctrl+C would still detach, just without a word.
+    let _ = ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst));
+
+    let launched = Instant::now();
+    let mut started = None;
+    loop {
+        if interrupted.load(Ordering::SeqCst) {
+            let played = started.map(|start: Instant| start.elapsed());
+            return Session::Detached(played.unwrap_or_default());
+        }
+        let running = running_appid(steam_type).as_deref() == Some(appid);
+        match (started, running) {
+            (None, true) => started = Some(Instant::now()),
+            (None, false) if launched.elapsed() > STARTUP_GRACE => return Session::NeverStarted,
+            (Some(start), false) => return Session::Ended(start.elapsed()),
+            (Some(start), true) if timeout.is_some_and(|timeout| start.elapsed() >= timeout) => {
+                return Session::TimedOut
+            }
+            _ => {}
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Executables of Steam's own processes, which must survive stopping a game
+/// even when they run with the game's path as their first argument.
+#[cfg(not(target_os = "windows"))]
+const STEAM_PROCESSES: [&str; 6] = [
+    "steam",
+    "steam.sh",
+    "steam_osx",
+    "steamwebhelper",
+    "steam-runtime-launch-client",
+    "steam-runtime-launcher-service",
+];
+
+/// Whether `program` is one of Steam's own processes.
+#[cfg(not(target_os = "windows"))]
+fn is_steam_process(program: &Path) -> bool {
+    program
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| STEAM_PROCESSES.contains(&name))
+}
+
+/// Whether the process with the executable `exe` and the nul separated
+/// `cmdline` is one of the game's, other than Steam's. Proton games run
+/// through Wine, whose executable is elsewhere, with the game's as argv[0].
+#[cfg(target_os = "linux")]
+fn runs_from(exe: &Path, cmdline: &[u8], install_path: &Path) -> bool {
+    if is_steam_process(exe) {
+        return false;
+    }
+    let argv0 = cmdline.split(|&byte| byte == 0).next().unwrap_or_default();
+    let argv0 = String::from_utf8_lossy(argv0);
+    // Wine can give it as a Windows path, on the drive mapped to `/`.
+    let argv0 = match argv0.strip_prefix("Z:").or(argv0.strip_prefix("z:")) {
+        Some(path) => path.replace('\\', "/"),
+        None => argv0.into_owned(),
+    };
+    exe.starts_with(install_path) || Path::new(&argv0).starts_with(install_path)
+}
+
+/// Processes running from `install_path`, other than ours and Steam's.
+#[cfg(target_os = "linux")]
+fn game_processes(install_path: &Path) -> std::io::Result<Vec<String>> {
+    let own_pid = std::process::id().to_string();
+    let mut pids = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let pid = entry?.file_name().to_string_lossy().into_owned();
+        if !pid.bytes().all(|byte| byte.is_ascii_digit()) || pid == own_pid {
+            continue;
+        }
+        let process = Path::new("/proc").join(&pid);
+        // Gone already, or someone else's.
+        let (exe, cmdline) = match (
+            std::fs::read_link(process.join("exe")),
+            std::fs::read(process.join("cmdline")),
+        ) {
+            (Ok(exe), Ok(cmdline)) => (exe, cmdline),
+            _ => continue,
+        };
+        if runs_from(&exe, &cmdline, install_path) {
+            pids.push(pid);
+        }
+    }
+    Ok(pids)
+}
+
+/// Processes running from `install_path`, other than ours and Steam's.
+#[cfg(target_os = "macos")]
+fn game_processes(install_path: &Path) -> std::io::Result<Vec<String>> {
+    // comm is the whole path of the executable, spaces included.
+    let output = Command::new("ps").args(["-axo", "pid=,comm="]).output()?;
+    let own_pid = std::process::id().to_string();
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().split_once(' '))
+        .filter(|(pid, program)| {
+            let program = Path::new(program.trim_start());
+            *pid != own_pid && program.starts_with(install_path) && !is_steam_process(program)
+        })
+        .map(|(pid, _)| pid.to_string())
+        .collect())
+}
+
+/// Terminate every process running from `install_path` and return how many
+/// were found.
+#[cfg(not(target_os = "windows"))]
+pub fn stop_game(install_path: &Path) -> std::io::Result<usize> {
+    let pids = game_processes(install_path)?;
+    if !pids.is_empty() {
+        Command::new("kill").args(&pids).status()?;
+    }
+    Ok(pids.len())
+}
+
+/// Terminate every process running from `install_path` and return how many
+/// were found.
+#[cfg(target_os = "windows")]
+pub fn stop_game(install_path: &Path) -> std::io::Result<usize> {
+    // Only processes whose executable is in the game folder, Steam's never is.
+    let filter = format!(
+        "$p = Get-Process | Where-Object {{ $_.Path -like '{}\\*' }}; $p | Stop-Process -Force; $p.Count",
+        install_path.display().to_string().replace('\'', "''")
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &filter])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .unwrap_or(0))
+}
+
+/// Turn a failed exit status into an error.
+#[cfg(not(target_os = "windows"))]
+fn check_status(status: std::process::ExitStatus) -> std::io::Result<()> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(std::io::Error::other(status.to_string())),
+    }
+}
+
+/// Show a desktop notification, with `image` as its icon if given.
+#[cfg(target_os = "linux")]
+pub fn notify(title: &str, message: &str, image: Option<&Path>) -> std::io::Result<()> {
+    let mut command = Command::new("notify-send");
+    if let Some(image) = image {
+        command.arg("--icon").arg(image);
+    }
+    check_status(command.args([title, message]).status()?)
+}
+
+/// Show a desktop notification, with `image` in it if terminal-notifier is
+/// installed to show it.
+#[cfg(target_os = "macos")]
+pub fn notify(title: &str, message: &str, image: Option<&Path>) -> std::io::Result<()> {
+    let mut command = Command::new("terminal-notifier");
+    command.args(["-title", title, "-message", message]);
+    if let Some(image) = image {
+        command.arg("-contentImage").arg(image);
+    }
+    if let Ok(status) = command.status() {
+        return check_status(status);
+    }
+    let script = format!("display notification {:?} with title {:?}", message, title);
+    check_status(Command::new("osascript").args(["-e", &script]).status()?)
+}
+
+/// Show a message box, in place of a notification.
+#[cfg(target_os = "windows")]
+pub fn notify(title: &str, message: &str, _image: Option<&Path>) -> std::io::Result<()> {
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.MessageBox]::Show('{}', '{}')",
+        message.replace('\'', "''"),
+        title.replace('\'', "''")
+    );
+    // The message box waits to be closed, so don't wait for it.
+    Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_game_runs_from_its_install_dir() {
+        let install_path = Path::new("/games/steamapps/common/Portal");
+        let runs =
+            |exe: &str, cmdline: &str| runs_from(Path::new(exe), cmdline.as_bytes(), install_path);
+        assert!(runs(
+            "/games/steamapps/common/Portal/hl2_linux",
+            "./hl2_linux\0-game\0portal\0"
+        ));
+        // Proton, with the game given both ways Wine does.
+        assert!(runs(
+            "/usr/bin/wine64-preloader",
+            "/games/steamapps/common/Portal/portal.exe\0"
+        ));
+        assert!(runs(
+            "/usr/bin/wine64-preloader",
+            "Z:\\games\\steamapps\\common\\Portal\\portal.exe\0"
+        ));
+        // Only mentioning the game folder doesn't make it the game's.
+        assert!(!runs(
+            "/usr/bin/rsync",
+            "rsync\0-a\0/games/steamapps/common/Portal/\0/backup/\0"
+        ));
+        assert!(!runs(
+            "/home/user/.steam/steam/ubuntu12_32/steam-runtime-launch-client",
+            "/games/steamapps/common/Portal/hl2.sh\0"
+        ));
+    }
+}
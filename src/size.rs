@@ -0,0 +1,52 @@
+/// Units accepted by `parse_size`, lowercase, with their size in bytes.
+const UNITS: [(&str, u64); 9] = [
+    ("b", 1),
+    ("kb", 1000),
+    ("mb", 1000 * 1000),
+    ("gb", 1000 * 1000 * 1000),
+    ("tb", 1000 * 1000 * 1000 * 1000),
+    ("kib", 1 << 10),
+    ("mib", 1 << 20),
+    ("gib", 1 << 30),
+    ("tib", 1 << 40),
+];
+
+/// Parse a human friendly size like `500MB` or `20 GiB` into bytes. A number
+/// without unit is in GB.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size \"{}\"", size))?;
+    let unit = unit.trim().to_lowercase();
+    let multiplier = if unit.is_empty() {
+        1000 * 1000 * 1000
+    } else {
+        UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, multiplier)| *multiplier)
+            .ok_or_else(|| format!("unknown size unit \"{}\", use e.g. MB or GiB", unit))?
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Format bytes for humans, e.g. `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
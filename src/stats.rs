@@ -0,0 +1,93 @@
+use crate::size::format_size;
+use serde::Serialize;
+use std::path::PathBuf;
+use steam_randomiser::Game;
+
+/// Games of one library, for `--stats`.
+#[derive(Serialize)]
+struct LibraryCount {
+    path: PathBuf,
+    games: usize,
+}
+
+/// Games dropped by one step of the filtering, for `--stats`.
+#[derive(Serialize)]
+struct Exclusion {
+    reason: &'static str,
+    games: usize,
+}
+
+/// What the scan found and what each filter took out of it.
+///
+/// The serialized form is printed by `--stats --output json`, so only add
+/// fields to it.
+#[derive(Serialize)]
+pub struct LibraryStats {
+    found: usize,
+    /// Bytes used by the games found, those of unknown size count as 0.
+    size_on_disk: u64,
+    libraries: Vec<LibraryCount>,
+    excluded: Vec<Exclusion>,
+    remaining: usize,
+}
+
+impl LibraryStats {
+    /// Count the games a scan found, before any filtering.
+    pub fn new(games: &[Game]) -> Self {
+        let mut libraries: Vec<LibraryCount> = Vec::new();
+        for game in games {
+            match libraries
+                .iter_mut()
+                .find(|count| count.path == game.library)
+            {
+                Some(count) => count.games += 1,
+                None => libraries.push(LibraryCount {
+                    path: game.library.clone(),
+                    games: 1,
+                }),
+            }
+        }
+        LibraryStats {
+            found: games.len(),
+            size_on_disk: games.iter().map(|game| game.size_on_disk).sum(),
+            libraries,
+            excluded: Vec::new(),
+            remaining: games.len(),
+        }
+    }
+
+    /// Record that `reason` took the games from `before` down to what's left
+    /// in `games`.
+    pub fn exclude(&mut self, reason: &'static str, before: usize, games: &[Game]) {
+        self.excluded.push(Exclusion {
+            reason,
+            games: before.saturating_sub(games.len()),
+        });
+        self.remaining = games.len();
+    }
+
+    /// Count games added to `games` after the scan, like non-Steam shortcuts,
+    /// as found too.
+    pub fn add(&mut self, games: &[Game]) {
+        self.found += games.len().saturating_sub(self.remaining);
+        self.remaining = games.len();
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{} games found, {} on disk",
+            self.found,
+            format_size(self.size_on_disk)
+        );
+        for library in &self.libraries {
+            println!("  {}\t{} games", library.path.display(), library.games);
+        }
+        if !self.excluded.is_empty() {
+            println!("Excluded:");
+        }
+        for exclusion in &self.excluded {
+            println!("  {}\t{} games", exclusion.reason, exclusion.games);
+        }
+        println!("{} games left to pick from", self.remaining);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::unix_now;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const STORE_CACHE_FILE: &str = "steam_randomiser/store.json";
+
+const APPDETAILS_URL: &str = "https://store.steampowered.com/api/appdetails";
+
+/// Names of the tags players put on store pages, by id.
+const TAG_NAMES_URL: &str = "https://store.steampowered.com/tagdata/populartags/english";
+
+/// How long tag names are reused before fetching them again, new tags are
+/// rare.
+pub const TAG_NAMES_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Bump when `AppDetails` gains fields, so details cached without them are
+/// fetched again.
+const STORE_CACHE_VERSION: u32 = 3;
+
+/// Store categories meaning a game supports or requires VR.
+const VR_CATEGORIES: [u32; 3] = [31, 53, 54];
+
+/// Store categories meaning several players can play together on one machine,
+/// "Shared/Split Screen" ones and "Remote Play Together".
+const LOCAL_MULTIPLAYER_CATEGORIES: [u32; 4] = [24, 37, 39, 44];
+
+/// Store categories meaning several players can play together, online or not.
+const MULTIPLAYER_CATEGORIES: [u32; 12] = [1, 9, 24, 27, 36, 37, 38, 39, 44, 47, 48, 49];
+
+/// What the Steam store knows about a game, as far as we care.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppDetails {
+    /// Kind of app, like "game", "dlc" or "music".
+    pub app_type: String,
+    /// Ids of the store categories ("Single-player", "VR Only", ...).
+    pub categories: Vec<u32>,
+    /// "full" or "partial", `None` when the game has no controller support.
+    pub controller_support: Option<String>,
+}
+
+impl AppDetails {
+    pub fn is_vr(&self) -> bool {
+        self.categories.iter().any(|c| VR_CATEGORIES.contains(c))
+    }
+
+    /// Whether several players can play the game together, on one machine if
+    /// `local`.
+    pub fn is_multiplayer(&self, local: bool) -> bool {
+        let categories = match local {
+            true => &LOCAL_MULTIPLAYER_CATEGORIES[..],
+            false => &MULTIPLAYER_CATEGORIES[..],
+        };
+        self.categories.iter().any(|c| categories.contains(c))
+    }
+
+    /// Whether the app is a soundtrack or other music.
+    pub fn is_music(&self) -> bool {
+        self.app_type == "music"
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StoreCache {
+    version: u32,
+    /// Details by appid, `None` when the store has no page for the app.
+    apps: HashMap<String, Option<AppDetails>>,
+    /// Names of the store tags by id, and the unix time they were fetched.
+    #[serde(default)]
+    tag_names: HashMap<u32, String>,
+    #[serde(default)]
+    tag_names_fetched: u64,
+}
+
+/// Store details of games, fetched from the store API the first time they're
+/// needed and cached on disk afterwards.
+pub struct Store {
+    path: Option<PathBuf>,
+    cache: StoreCache,
+    changed: bool,
+    agent: ureq::Agent,
+    /// Why the store couldn't be reached, once it couldn't. Nothing more is
+    /// fetched after that, each try would wait for the timeout again.
+    failed: Option<String>,
+    /// Apps whose details weren't known because of that.
+    unclassified: usize,
+}
+
+impl Store {
+    pub fn open() -> Self {
+        let path = dirs::cache_dir().map(|dir| dir.join(STORE_CACHE_FILE));
+        let cache = path
+            .as_deref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<StoreCache>(&contents).ok())
+            .filter(|cache| cache.version == STORE_CACHE_VERSION)
+            .unwrap_or_else(|| StoreCache {
+                version: STORE_CACHE_VERSION,
+                ..Default::default()
+            });
+        Store {
+            path,
+            cache,
+            changed: false,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+            failed: None,
+            unclassified: 0,
+        }
+    }
+
+    /// Details of the game, or `None` if they aren't known, e.g. because the
+    /// store couldn't be reached.
+    pub fn details(&mut self, appid: &str) -> Option<&AppDetails> {
+        if !self.cache.apps.contains_key(appid) {
+            if self.failed.is_some() {
+                self.unclassified += 1;
+                return None;
+            }
+            // Network failures aren't cached so they get retried next time.
+            match self.fetch(appid) {
+                Ok(details) => {
+                    self.cache.apps.insert(appid.to_string(), details);
+                    self.changed = true;
+                }
+                Err(err) => {
+                    self.failed = Some(err.to_string());
+                    self.unclassified += 1;
+                    return None;
+                }
+            }
+        }
+        self.cache.apps.get(appid)?.as_ref()
+    }
+
+    fn fetch(&self, appid: &str) -> Result<Option<AppDetails>, Box<dyn std::error::Error>> {
+        let response: Value = self
+            .agent
+            .get(APPDETAILS_URL)
+            .query("appids", appid)
+            .query("filters", "basic,categories")
+            .call()?
+            .into_json()?;
+        Ok(parse_appdetails(appid, &response))
+    }
+
+    /// Names of the store tags by id, empty if the store couldn't be reached
+    /// and none are cached. Cached names are used while they're recent, or
+    /// while the store can't be reached, see `tag_names_age`.
+    pub fn tag_names(&mut self) -> &HashMap<u32, String> {
+        if self.tag_names_age() > TAG_NAMES_MAX_AGE || self.cache.tag_names.is_empty() {
+            if let Ok(tag_names) = self.fetch_tag_names() {
+                self.cache.tag_names = tag_names;
+                self.cache.tag_names_fetched = unix_now();
+                self.changed = true;
+            }
+        }
+        &self.cache.tag_names
+    }
+
+    /// How long ago the tag names were fetched.
+    pub fn tag_names_age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.cache.tag_names_fetched))
+    }
+
+    fn fetch_tag_names(&self) -> Result<HashMap<u32, String>, Box<dyn std::error::Error>> {
+        let response: Value = self.agent.get(TAG_NAMES_URL).call()?.into_json()?;
+        Ok(response
+            .as_array()
+            .ok_or("unexpected tag list")?
+            .iter()
+            .filter_map(|tag| {
+                let id = tag["tagid"].as_u64()? as u32;
+                Some((id, tag["name"].as_str()?.to_string()))
+            })
+            .collect())
+    }
+
+    /// Write newly fetched details to the cache, and tell how many apps were
+    /// left unclassified if the store couldn't be reached.
+    pub fn save(&self) {
+        if let Some(err) = &self.failed {
+            eprintln!(
+                "Couldn't reach the Steam store ({}), {} apps left unclassified",
+                err, self.unclassified
+            );
+        }
+        let path = match &self.path {
+            Some(path) if self.changed => path,
+            _ => return,
+        };
+        if let Err(err) = write_cache(path, &self.cache) {
+            eprintln!("Couldn't save store cache {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// The details of `appid` in an appdetails response, `None` when the store
+/// has no page for it.
+fn parse_appdetails(appid: &str, response: &Value) -> Option<AppDetails> {
+    let app = &response[appid];
+    if app["success"] != Value::Bool(true) {
+        return None;
+    }
+    let categories = app["data"]["categories"]
+        .as_array()
+        .map(|categories| {
+            categories
+                .iter()
+                .filter_map(|category| category["id"].as_u64())
+                .map(|id| id as u32)
+                .collect()
+        })
+        .unwrap_or_default();
+    let app_type = app["data"]["type"].as_str().unwrap_or_default().to_string();
+    let controller_support = app["data"]["controller_support"]
+        .as_str()
+        .map(str::to_string);
+    Some(AppDetails {
+        app_type,
+        categories,
+        controller_support,
+    })
+}
+
+fn write_cache(path: &Path, cache: &StoreCache) -> std::io::Result<()> {
+    crate::write_atomic(path, serde_json::to_string(cache)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(appid: &str, response: &str) -> Option<AppDetails> {
+        parse_appdetails(appid, &serde_json::from_str(response).unwrap())
+    }
+
+    #[test]
+    fn single_player_games_are_not_multiplayer() {
+        let response = r#"{"220": {"success": true, "data": {
+            "type": "game",
+            "categories": [
+                {"id": 2, "description": "Single-player"},
+                {"id": 22, "description": "Steam Achievements"}
+            ]
+        }}}"#;
+        let details = details("220", response).unwrap();
+        assert_eq!(details.app_type, "game");
+        assert_eq!(details.categories, [2, 22]);
+        assert!(!details.is_multiplayer(false));
+        assert!(!details.is_multiplayer(true));
+    }
+
+    #[test]
+    fn local_co_op_games_are_multiplayer_on_one_machine() {
+        let response = r#"{"620": {"success": true, "data": {
+            "type": "game",
+            "controller_support": "full",
+            "categories": [
+                {"id": 2, "description": "Single-player"},
+                {"id": 9, "description": "Co-op"},
+                {"id": 39, "description": "Shared/Split Screen Co-op"}
+            ]
+        }}}"#;
+        let details = details("620", response).unwrap();
+        assert_eq!(details.controller_support.as_deref(), Some("full"));
+        assert!(details.is_multiplayer(false));
+        assert!(details.is_multiplayer(true));
+    }
+
+    #[test]
+    fn online_pvp_games_are_not_multiplayer_on_one_machine() {
+        let response = r#"{"730": {"success": true, "data": {
+            "type": "game",
+            "categories": [
+                {"id": 1, "description": "Multi-player"},
+                {"id": 36, "description": "Online PvP"},
+                {"id": 49, "description": "PvP"}
+            ]
+        }}}"#;
+        let details = details("730", response).unwrap();
+        assert!(details.is_multiplayer(false));
+        assert!(!details.is_multiplayer(true));
+    }
+
+    #[test]
+    fn apps_without_a_store_page_have_no_details() {
+        assert!(details("1", r#"{"1": {"success": false}}"#).is_none());
+    }
+}
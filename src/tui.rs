@@ -0,0 +1,331 @@
+use crate::{pick::choose_games, size::format_size, Weighting};
+use rand::rngs::StdRng;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use steam_randomiser::{store::Store, Game};
+
+/// Largest install sizes `s` cycles through, `None` for any size.
+const SIZE_STEPS: [Option<u64>; 4] = [
+    None,
+    Some(5_000_000_000),
+    Some(20_000_000_000),
+    Some(50_000_000_000),
+];
+
+/// How many games the highlight goes over before landing on the pick, and
+/// how long it stays on each, slowing down towards the end.
+const SPIN_STEPS: u64 = 25;
+const SPIN_FIRST_DELAY: Duration = Duration::from_millis(15);
+const SPIN_SLOWDOWN: Duration = Duration::from_millis(6);
+
+/// How long to wait for a key before redrawing.
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+const HELP: &str =
+    "↑/↓ move  r reroll  Enter launch  b blacklist  n never played  s size  v VR  q quit";
+
+/// The highlight rolling down the list towards the pick.
+struct Spin {
+    target: String,
+    steps_left: u64,
+    next_step: Instant,
+}
+
+struct App<'a> {
+    games: Vec<&'a Game>,
+    weighting: Option<Weighting>,
+    blacklist_file: Option<PathBuf>,
+    /// Appids blacklisted from the TUI.
+    blacklisted: HashSet<String>,
+    never_played: bool,
+    exclude_vr: bool,
+    /// Index in `SIZE_STEPS`.
+    max_size: usize,
+    /// Whether each game is a VR one, looked up once VR games are excluded.
+    is_vr: HashMap<String, bool>,
+    list: ListState,
+    spin: Option<Spin>,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    /// The games left by the filters toggled on, in the same way as the
+    /// options of the same name.
+    fn visible(&self) -> Vec<&'a Game> {
+        let max_size = SIZE_STEPS[self.max_size].unwrap_or(u64::MAX);
+        self.games
+            .iter()
+            .copied()
+            .filter(|game| !self.blacklisted.contains(&game.appid))
+            .filter(|game| !self.never_played || game.is_unplayed())
+            .filter(|game| game.fits_size(0, max_size, false))
+            .filter(|game| !self.exclude_vr || self.is_vr.get(&game.appid) != Some(&true))
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&'a Game> {
+        let index = self.list.selected()?;
+        self.visible().get(index).copied()
+    }
+
+    /// Keep the highlight on the same game if it's still shown after the
+    /// filters changed.
+    fn refilter(&mut self, highlighted: Option<&Game>) {
+        let visible = self.visible();
+        let index = highlighted
+            .and_then(|game| visible.iter().position(|other| other.appid == game.appid))
+            .unwrap_or(0);
+        self.list
+            .select((!visible.is_empty()).then(|| index.min(visible.len() - 1)));
+    }
+
+    /// Start rolling the highlight towards a new random pick.
+    fn reroll(&mut self, rng: &mut StdRng) {
+        let visible = self.visible();
+        let target = match choose_games(&visible, 1, self.weighting, rng).first() {
+            Some(game) => game.appid.clone(),
+            None => {
+                self.status = "No games left with these filters.".to_string();
+                return;
+            }
+        };
+        // Start far enough up the list to roll down onto the pick.
+        let position = visible.iter().position(|game| game.appid == target);
+        let len = visible.len() as u64;
+        let start = (position.unwrap_or(0) as u64 + len - SPIN_STEPS % len) % len;
+        self.list.select(Some(start as usize));
+        self.spin = Some(Spin {
+            target,
+            steps_left: SPIN_STEPS,
+            next_step: Instant::now() + SPIN_FIRST_DELAY,
+        });
+        self.status.clear();
+    }
+
+    /// Move the highlight along if it's time, returns when to come back.
+    fn spin(&mut self) -> Duration {
+        let visible_len = self.visible().len();
+        let spin = match &mut self.spin {
+            Some(spin) => spin,
+            None => return IDLE_POLL,
+        };
+        let now = Instant::now();
+        if now < spin.next_step {
+            return spin.next_step - now;
+        }
+        spin.steps_left -= 1;
+        let delay = SPIN_FIRST_DELAY + SPIN_SLOWDOWN * (SPIN_STEPS - spin.steps_left) as u32;
+        spin.next_step = now + delay;
+        if spin.steps_left == 0 || visible_len == 0 {
+            self.land();
+            return IDLE_POLL;
+        }
+        let next = self.list.selected().map_or(0, |index| index + 1) % visible_len;
+        self.list.select(Some(next));
+        delay
+    }
+
+    /// Stop spinning, on the pick.
+    fn land(&mut self) {
+        if let Some(spin) = self.spin.take() {
+            let visible = self.visible();
+            let index = visible.iter().position(|game| game.appid == spin.target);
+            self.list.select(index.or(self.list.selected()));
+        }
+    }
+
+    fn toggle_vr(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        self.exclude_vr = !self.exclude_vr;
+        if self.exclude_vr && self.is_vr.len() < self.games.len() {
+            self.status = "Looking up VR games in the Steam store...".to_string();
+            terminal.draw(|frame| self.draw(frame))?;
+            let mut store = Store::open();
+            for game in &self.games {
+                // Unknown games are kept, like with --exclude-vr.
+                if let Some(details) = store.details(&game.appid) {
+                    self.is_vr.insert(game.appid.clone(), details.is_vr());
+                }
+            }
+            store.save();
+            self.status.clear();
+        }
+        Ok(())
+    }
+
+    /// Add the highlighted game to the blacklist file, and hide it.
+    fn blacklist(&mut self) {
+        let game = match self.selected() {
+            Some(game) => game,
+            None => return,
+        };
+        let path = match &self.blacklist_file {
+            Some(path) => path,
+            None => {
+                self.status = "No blacklist file to add games to.".to_string();
+                return;
+            }
+        };
+        match append_to_blacklist(path, game) {
+            Ok(()) => {
+                self.status = format!("Blacklisted \"{}\".", game.name);
+                self.blacklisted.insert(game.appid.clone());
+                self.refilter(None);
+            }
+            Err(err) => {
+                self.status = format!("Couldn't write {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let visible = self.visible();
+        let items = visible.iter().map(|game| {
+            let size = match game.size_on_disk {
+                0 => String::new(),
+                size => format!("  ({})", format_size(size)),
+            };
+            ListItem::new(format!("{}{}", game.name, size))
+        });
+        let title = format!(
+            " Steam Randomiser: {} of {} games ",
+            visible.len(),
+            self.games.len()
+        );
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(
+                Style::new()
+                    .fg(Color::Black)
+                    .bg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let max_size = SIZE_STEPS[self.max_size].map_or("any".to_string(), format_size);
+        let filters = format!(
+            "Never played: {}  Max size: {}  Exclude VR: {}  {}",
+            on_off(self.never_played),
+            max_size,
+            on_off(self.exclude_vr),
+            self.status
+        );
+
+        let [list_area, filters_area, help_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+        frame.render_widget(Line::raw(filters), filters_area);
+        frame.render_widget(
+            Line::styled(HELP, Style::new().add_modifier(Modifier::DIM)),
+            help_area,
+        );
+    }
+}
+
+/// Add `game` at the end of the blacklist file, creating it if needed.
+fn append_to_blacklist(path: &Path, game: &Game) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "# {}\nappid:{}", game.name, game.appid)
+}
+
+fn run<'a>(
+    terminal: &mut DefaultTerminal,
+    app: &mut App<'a>,
+    rng: &mut StdRng,
+) -> std::io::Result<Option<&'a Game>> {
+    app.reroll(rng);
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        let wait = app.spin();
+        if !event::poll(wait)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        let highlighted = app.selected();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            // Enter while spinning skips to the pick.
+            KeyCode::Enter if app.spin.is_some() => app.land(),
+            KeyCode::Enter => {
+                if let Some(game) = highlighted {
+                    return Ok(Some(game));
+                }
+            }
+            _ if app.spin.is_some() => {}
+            KeyCode::Char('r') => app.reroll(rng),
+            KeyCode::Char('b') => app.blacklist(),
+            KeyCode::Char('n') => {
+                app.never_played = !app.never_played;
+                app.refilter(highlighted);
+            }
+            KeyCode::Char('s') => {
+                app.max_size = (app.max_size + 1) % SIZE_STEPS.len();
+                app.refilter(highlighted);
+            }
+            KeyCode::Char('v') => {
+                app.toggle_vr(terminal)?;
+                app.refilter(highlighted);
+            }
+            KeyCode::Down | KeyCode::Char('j') => app.list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => app.list.select_previous(),
+            _ => {}
+        }
+    }
+}
+
+/// Show `games` full screen and spin for a random pick, letting the user
+/// reroll and narrow down the games. Returns the game to launch, or `None`
+/// if the user quit.
+///
+/// Games blacklisted with `b` are added to `blacklist_file`.
+pub fn run_tui<'a>(
+    games: &[&'a Game],
+    weighting: Option<Weighting>,
+    rng: &mut StdRng,
+    blacklist_file: Option<PathBuf>,
+) -> std::io::Result<Option<&'a Game>> {
+    let mut app = App {
+        games: games.to_vec(),
+        weighting,
+        blacklist_file,
+        blacklisted: HashSet::new(),
+        never_played: false,
+        exclude_vr: false,
+        max_size: 0,
+        is_vr: HashMap::new(),
+        list: ListState::default(),
+        spin: None,
+        status: String::new(),
+    };
+    // Also puts the terminal back if we panic.
+    let mut terminal = ratatui::try_init()?;
+    let result = run(&mut terminal, &mut app, rng);
+    ratatui::restore();
+    result
+}
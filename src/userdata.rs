@@ -0,0 +1,257 @@
+use crate::{
+    binary_vdf::parse_binary_vdf,
+    vdf::{parse_vdf, VdfError, VdfValue},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+/// SteamID64 of the account with id 0, `userdata` folders are named after
+/// the account id.
+const STEAMID64_BASE: u64 = 76561197960265728;
+
+/// Steam accounts that logged in on this install.
+const LOGIN_USERS_FILE: &str = "config/loginusers.vdf";
+
+/// Path of the apps of a user in their `localconfig.vdf`.
+const APPS_KEY: [&str; 5] = ["UserLocalConfigStore", "Software", "Valve", "Steam", "apps"];
+
+/// Shortcut ids only have their upper half, this is the lower one.
+const SHORTCUT_ID_FLAG: u64 = 0x02000000;
+
+/// The account id a `userdata` folder is named after, from either itself or
+/// a SteamID64.
+fn account_id(steam_id: u64) -> u64 {
+    steam_id.checked_sub(STEAMID64_BASE).unwrap_or(steam_id)
+}
+
+/// The SteamID64 of an account, from either itself or its account id.
+pub fn steam_id64(steam_id: u64) -> u64 {
+    match steam_id < STEAMID64_BASE {
+        true => steam_id + STEAMID64_BASE,
+        false => steam_id,
+    }
+}
+
+/// The `userdata` folder of `steam_id`, or of every Steam account that used
+/// this install.
+pub fn user_dirs(steam_root: &Path, steam_id: Option<u64>) -> Vec<PathBuf> {
+    let userdata = steam_root.join("userdata");
+    if let Some(steam_id) = steam_id {
+        return vec![userdata.join(account_id(steam_id).to_string())];
+    }
+    match std::fs::read_dir(userdata) {
+        Ok(users) => users
+            .filter_map(Result::ok)
+            .map(|user| user.path())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// A Steam account that logged in on this install.
+pub struct LoginUser {
+    pub steam_id: u64,
+    pub account_name: String,
+    pub persona_name: String,
+    /// Whether it's the account that logged in last.
+    pub most_recent: bool,
+}
+
+impl LoginUser {
+    /// The id its `userdata` folder is named after.
+    pub fn account_id(&self) -> u64 {
+        account_id(self.steam_id)
+    }
+}
+
+/// Read the accounts that logged in on this install from `loginusers.vdf`.
+pub fn read_login_users(steam_root: &Path) -> Result<Vec<LoginUser>, VdfError> {
+    let vdf = parse_vdf(&steam_root.join(LOGIN_USERS_FILE))?;
+    let users = match vdf.get("users") {
+        Some(VdfValue::Block(users)) => users,
+        _ => return Err(VdfError::MissingKey("users".to_string())),
+    };
+    Ok(users
+        .iter()
+        .filter_map(|(steam_id, user)| {
+            let field = |key: &str| {
+                user.get(key)
+                    .and_then(VdfValue::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            Some(LoginUser {
+                steam_id: steam_id.parse().ok()?,
+                account_name: field("AccountName"),
+                persona_name: field("PersonaName"),
+                most_recent: field("MostRecent") == "1",
+            })
+        })
+        .collect())
+}
+
+/// The SteamID64 or account id of `user`, which is either of those ids or the
+/// persona or account name of an account that logged in. Without `user`, the
+/// account that logged in last is picked, if Steam remembers one.
+pub fn select_user(steam_root: &Path, user: Option<&str>) -> Result<Option<u64>, String> {
+    if let Some(steam_id) = user.and_then(|user| user.parse::<u64>().ok()) {
+        return Ok(Some(steam_id));
+    }
+    let login_users = read_login_users(steam_root);
+    let user = match user {
+        Some(user) => user,
+        None => {
+            let users = login_users.unwrap_or_default();
+            return Ok(users
+                .iter()
+                .find(|login| login.most_recent)
+                .map(|login| login.steam_id));
+        }
+    };
+    let users = login_users.map_err(|err| {
+        format!(
+            "couldn't read {}: {}",
+            steam_root.join(LOGIN_USERS_FILE).display(),
+            err
+        )
+    })?;
+    users
+        .iter()
+        .find(|login| {
+            login.persona_name.eq_ignore_ascii_case(user)
+                || login.account_name.eq_ignore_ascii_case(user)
+        })
+        .map(|login| Some(login.steam_id))
+        .ok_or_else(|| format!("no Steam account named \"{}\" logged in here", user))
+}
+
+/// Find the `localconfig.vdf` of `steam_id`, or of every Steam account that
+/// used this install.
+fn local_configs(steam_root: &Path, steam_id: Option<u64>) -> Vec<VdfValue> {
+    user_dirs(steam_root, steam_id)
+        .iter()
+        .filter_map(|user| parse_vdf(&user.join("config/localconfig.vdf")).ok())
+        .collect()
+}
+
+/// What Steam remembers locally about a user playing a game.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AppStats {
+    /// Minutes played.
+    pub playtime: Option<u64>,
+    /// Unix time the game was last played.
+    pub last_played: Option<u64>,
+}
+
+/// Read the playtime and last played time of each game of `steam_id`, by
+/// appid. Without an account, when several accounts played a game, the
+/// longest playtime and the latest time are kept.
+pub fn read_app_stats(steam_root: &Path, steam_id: Option<u64>) -> HashMap<String, AppStats> {
+    let mut stats = HashMap::<String, AppStats>::new();
+    for config in local_configs(steam_root, steam_id) {
+        let apps = config.lookup(&APPS_KEY);
+        let apps = match apps {
+            Some(VdfValue::Block(apps)) => apps,
+            _ => continue,
+        };
+        for (appid, app) in apps {
+            let number = |key: &str| {
+                app.get(key)
+                    .and_then(VdfValue::as_str)
+                    .and_then(|n| n.parse::<u64>().ok())
+            };
+            let app_stats = stats.entry(appid.clone()).or_default();
+            app_stats.playtime = app_stats.playtime.max(number("Playtime"));
+            app_stats.last_played = app_stats.last_played.max(number("LastPlayed"));
+        }
+    }
+    stats
+}
+
+/// Read the appids a user has in their local config. `steam_id` is either a
+/// SteamID64 or the account id their `userdata` folder is named after.
+pub fn read_user_apps(steam_root: &Path, steam_id: u64) -> Result<HashSet<String>, VdfError> {
+    let config = parse_vdf(
+        &steam_root
+            .join("userdata")
+            .join(account_id(steam_id).to_string())
+            .join("config/localconfig.vdf"),
+    )?;
+    match config.lookup(&APPS_KEY) {
+        Some(VdfValue::Block(apps)) => Ok(apps.iter().map(|(appid, _)| appid.clone()).collect()),
+        _ => Err(VdfError::MissingKey(APPS_KEY.join("/"))),
+    }
+}
+
+/// A non-Steam game added to the library.
+pub struct Shortcut {
+    /// Id to launch the shortcut with, in place of an appid.
+    pub game_id: String,
+    pub name: String,
+    /// Folder the shortcut is started in.
+    pub start_dir: PathBuf,
+}
+
+/// Read the non-Steam games of `steam_id`, or of every account if not given.
+pub fn read_shortcuts(steam_root: &Path, steam_id: Option<u64>) -> Vec<Shortcut> {
+    let mut shortcuts = Vec::<Shortcut>::new();
+    for user in user_dirs(steam_root, steam_id) {
+        let data = match std::fs::read(user.join("config/shortcuts.vdf")) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        let vdf = match parse_binary_vdf(&data) {
+            Ok(vdf) => vdf,
+            Err(err) => {
+                eprintln!("Warning: skipping shortcuts of {}: {}", user.display(), err);
+                continue;
+            }
+        };
+        let entries = match vdf.get("shortcuts") {
+            Some(VdfValue::Block(entries)) => entries,
+            _ => continue,
+        };
+        for (_, entry) in entries {
+            let field = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(VdfValue::as_str)
+                    .unwrap_or_default()
+                    // Paths are quoted for Steam's command line.
+                    .trim_matches('"')
+                    .to_string()
+            };
+            let name = field("AppName");
+            let exe = field("Exe");
+            let upper_id = match field("appid").parse::<i32>() {
+                Ok(appid) => appid as u32 as u64,
+                // Older Steam didn't store the id, it derived it like this.
+                Err(_) => (crc32(format!("\"{}\"{}", exe, name).as_bytes()) | 0x80000000) as u64,
+            };
+            let game_id = (upper_id << 32 | SHORTCUT_ID_FLAG).to_string();
+            if name.is_empty() || shortcuts.iter().any(|s| s.game_id == game_id) {
+                continue;
+            }
+            shortcuts.push(Shortcut {
+                game_id,
+                name,
+                start_dir: PathBuf::from(field("StartDir")),
+            });
+        }
+    }
+    shortcuts
+}
+
+/// CRC-32 as used by zlib, which legacy shortcut ids are based on.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
@@ -0,0 +1,334 @@
+use std::{fmt, path::Path};
+
+/// A node of a parsed VDF (Valve Data Format) document.
+///
+/// Blocks keep their entries in file order, and keys are looked up
+/// case-insensitively since Steam isn't consistent about casing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    Str(String),
+    Block(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    /// Get the value for `key` if this is a block containing it.
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Block(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            VdfValue::Str(_) => None,
+        }
+    }
+
+    /// Follow a path of keys through nested blocks.
+    pub fn lookup(&self, keys: &[&str]) -> Option<&VdfValue> {
+        keys.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::Str(s) => Some(s),
+            VdfValue::Block(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VdfError {
+    Io(std::io::Error),
+    /// The document ended in the middle of a string or block.
+    UnexpectedEof,
+    /// A token appeared where it isn't allowed, e.g. a stray `}`.
+    UnexpectedToken {
+        line: usize,
+        token: String,
+    },
+    /// A required key wasn't present in the document.
+    MissingKey(String),
+    /// A binary VDF file in a format we can't read.
+    UnsupportedVersion(u32),
+    /// A binary VDF value of a type we don't know.
+    UnknownType(u8),
+    /// Text that claims to be UTF-16 but isn't, or has stray nul bytes.
+    InvalidText,
+}
+
+impl fmt::Display for VdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VdfError::Io(err) => write!(f, "couldn't read file: {}", err),
+            VdfError::UnexpectedEof => write!(f, "unexpected end of file"),
+            VdfError::UnexpectedToken { line, token } => {
+                write!(f, "unexpected {:?} on line {}", token, line)
+            }
+            VdfError::MissingKey(key) => write!(f, "missing key {:?}", key),
+            VdfError::UnsupportedVersion(magic) => {
+                write!(f, "unsupported file version {:#010x}", magic)
+            }
+            VdfError::UnknownType(kind) => write!(f, "unknown value type {:#04x}", kind),
+            VdfError::InvalidText => write!(f, "not valid UTF-8 or UTF-16 text"),
+        }
+    }
+}
+
+impl std::error::Error for VdfError {}
+
+impl From<std::io::Error> for VdfError {
+    fn from(err: std::io::Error) -> Self {
+        VdfError::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn into_text(self) -> String {
+        match self {
+            Token::Str(s) => s,
+            Token::Open => "{".to_string(),
+            Token::Close => "}".to_string(),
+        }
+    }
+}
+
+struct Tokenizer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str) -> Self {
+        Tokenizer {
+            chars: input.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skip whitespace (including CRLF), `//` comments and `[$PLATFORM]`
+    /// conditionals, which we don't evaluate.
+    fn skip_trivia(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' {
+                self.bump();
+                if self.chars.peek() == Some(&'/') {
+                    while !matches!(self.bump(), Some('\n') | None) {}
+                }
+            } else if c == '[' {
+                while !matches!(self.bump(), Some(']') | None) {}
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, VdfError> {
+        self.skip_trivia();
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let token = match c {
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match self.bump().ok_or(VdfError::UnexpectedEof)? {
+                        '"' => break,
+                        '\\' => match self.bump().ok_or(VdfError::UnexpectedEof)? {
+                            'n' => s.push('\n'),
+                            't' => s.push('\t'),
+                            other => s.push(other),
+                        },
+                        other => s.push(other),
+                    }
+                }
+                Token::Str(s)
+            }
+            c => {
+                let mut s = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                    self.bump();
+                }
+                Token::Str(s)
+            }
+        };
+        Ok(Some(token))
+    }
+
+    /// Parse `key value` pairs until the end of the current block.
+    fn parse_block(&mut self, nested: bool) -> Result<Vec<(String, VdfValue)>, VdfError> {
+        let mut entries = Vec::new();
+        loop {
+            let line = self.line;
+            let key = match self.next_token()? {
+                Some(Token::Str(key)) => key,
+                Some(Token::Close) if nested => return Ok(entries),
+                None if !nested => return Ok(entries),
+                None => return Err(VdfError::UnexpectedEof),
+                Some(token) => {
+                    return Err(VdfError::UnexpectedToken {
+                        line,
+                        token: token.into_text(),
+                    })
+                }
+            };
+            let line = self.line;
+            let value = match self.next_token()? {
+                Some(Token::Str(value)) => VdfValue::Str(value),
+                Some(Token::Open) => VdfValue::Block(self.parse_block(true)?),
+                Some(token @ Token::Close) => {
+                    return Err(VdfError::UnexpectedToken {
+                        line,
+                        token: token.into_text(),
+                    })
+                }
+                None => return Err(VdfError::UnexpectedEof),
+            };
+            entries.push((key, value));
+        }
+    }
+}
+
+/// Parse VDF text into a tree whose root block holds the top level entries.
+pub fn parse_vdf_str(input: &str) -> Result<VdfValue, VdfError> {
+    // A byte order mark would end up in the first key.
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    Tokenizer::new(input)
+        .parse_block(false)
+        .map(VdfValue::Block)
+}
+
+/// Decode UTF-16 text, little endian unless `big_endian`. `None` if it
+/// isn't valid UTF-16.
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|pair| match big_endian {
+        true => u16::from_be_bytes([pair[0], pair[1]]),
+        false => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+/// Whether every nul byte of `bytes` is at an odd index for little endian,
+/// or an even one for big endian, as the high byte of an ASCII character in
+/// UTF-16 would be. `None` without any nul byte or with them on both sides.
+fn utf16_nul_pattern(bytes: &[u8]) -> Option<bool> {
+    let mut nul_at = bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == 0)
+        .map(|(index, _)| index % 2 == 0);
+    let big_endian = nul_at.next()?;
+    nul_at.all(|even| even == big_endian).then_some(big_endian)
+}
+
+/// Decode a text file Steam wrote. It's normally UTF-8, but some were saved
+/// as UTF-16, which is only assumed with a byte order mark or nul bytes
+/// alternating with ASCII. Anything else is read as UTF-8, replacing what
+/// isn't valid.
+fn decode_text(bytes: Vec<u8>) -> Result<String, VdfError> {
+    let utf16 = match bytes.as_slice() {
+        [0xFF, 0xFE, rest @ ..] => Some((rest, false)),
+        [0xFE, 0xFF, rest @ ..] => Some((rest, true)),
+        // Nul bytes can't be in UTF-8 text, they're either UTF-16 or garbage.
+        bytes if bytes.contains(&0) => match utf16_nul_pattern(bytes) {
+            Some(big_endian) => Some((bytes, big_endian)),
+            None => return Err(VdfError::InvalidText),
+        },
+        _ => None,
+    };
+    match utf16 {
+        Some((bytes, big_endian)) => decode_utf16(bytes, big_endian).ok_or(VdfError::InvalidText),
+        None => Ok(match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+        }),
+    }
+}
+
+/// Parse a VDF file such as an appmanifest or `libraryfolders.vdf`.
+pub fn parse_vdf(path: &Path) -> Result<VdfValue, VdfError> {
+    let contents = decode_text(std::fs::read(path)?)?;
+    parse_vdf_str(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn name(bytes: Vec<u8>) -> String {
+        let text = decode_text(bytes).unwrap();
+        let vdf = parse_vdf_str(&text).unwrap();
+        vdf.lookup(&["AppState", "name"])
+            .and_then(VdfValue::as_str)
+            .unwrap()
+            .to_string()
+    }
+
+    const MANIFEST: &str = "\"AppState\"\n{\n\t\"name\"\t\"Café\"\n}\n";
+
+    #[test]
+    fn decodes_utf8_with_bom() {
+        let bytes = [&[0xEF, 0xBB, 0xBF][..], MANIFEST.as_bytes()].concat();
+        assert_eq!(name(bytes), "Café");
+    }
+
+    #[test]
+    fn decodes_utf16le_with_bom() {
+        let bytes = [&[0xFF, 0xFE][..], &utf16le(MANIFEST)].concat();
+        assert_eq!(name(bytes), "Café");
+    }
+
+    #[test]
+    fn decodes_utf16le_without_bom() {
+        assert_eq!(name(utf16le(MANIFEST)), "Café");
+    }
+
+    #[test]
+    fn decodes_utf16be_without_bom() {
+        let bytes = MANIFEST.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(name(bytes), "Café");
+    }
+
+    #[test]
+    fn does_not_guess_utf16_without_nul_bytes() {
+        // No ASCII, so nothing to tell it's UTF-16 rather than broken UTF-8.
+        let text = decode_text(utf16le("日本語")).unwrap();
+        assert_ne!(text, "日本語");
+        assert!(text.contains(char::REPLACEMENT_CHARACTER));
+    }
+
+    #[test]
+    fn stray_nul_bytes_are_an_error() {
+        let bytes = b"\"AppState\"\0\0{\n}\n".to_vec();
+        assert!(matches!(decode_text(bytes), Err(VdfError::InvalidText)));
+    }
+}